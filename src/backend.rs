@@ -0,0 +1,209 @@
+use crate::converter::{ConvertError, ConvertOptions, OutputConfig, ProxyGroup};
+use crate::proxy::{Proxy, TYPED_PROXY_TYPES};
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashSet;
+
+/// Clash's built-in direct policy, used as the only member of 直接连接
+pub const DIRECT: &str = "DIRECT";
+
+/// Rule lists per client, used where a format has no GEOSITE database
+const RULE_LIST_URL: &str =
+    "https://raw.githubusercontent.com/blackmatrix7/ios_rule_script/master/rule";
+
+/// A proxy left out of the output and why
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DroppedProxy {
+    pub name: String,
+    pub reason: String,
+}
+
+/// A rendered configuration
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rendered {
+    pub content: String,
+    /// Proxies the format cannot express
+    pub dropped: Vec<DroppedProxy>,
+}
+
+/// An output format for the converted configuration
+pub trait Backend {
+    /// Render the config; proxies the format cannot express are left out and listed
+    fn render(
+        &self,
+        output: &OutputConfig,
+        options: &ConvertOptions,
+    ) -> Result<Rendered, ConvertError>;
+}
+
+/// Convert every proxy with `convert`, keeping the name of each converted proxy
+/// and collecting the ones it rejects
+pub fn convert_each<T>(
+    proxies: &[Proxy],
+    convert: impl Fn(&Proxy) -> Result<T, String>,
+) -> (Vec<(String, T)>, Vec<DroppedProxy>) {
+    let mut converted = Vec::new();
+    let mut dropped = Vec::new();
+    for proxy in proxies {
+        let name = proxy.name().unwrap_or_default();
+        match convert(proxy) {
+            Ok(value) => converted.push((name, value)),
+            Err(reason) => dropped.push(DroppedProxy { name, reason }),
+        }
+    }
+    (converted, dropped)
+}
+
+/// Why a format has no output for `proxy`
+pub fn unsupported(proxy: &Proxy) -> String {
+    let kind = proxy.type_name();
+    match proxy {
+        // A known type that did not fit its model
+        Proxy::Unknown(_) if TYPED_PROXY_TYPES.contains(&kind) => {
+            format!("invalid {} proxy", kind)
+        }
+        _ => format!("unsupported type '{}'", kind),
+    }
+}
+
+/// Check that a proxy fits a comma separated line, as these formats have no escaping:
+/// the name may contain neither `,` nor `=`, and no field may contain `,`.
+/// The reason never includes the value, which may be a password.
+pub fn check_line(name: &str, fields: &[String]) -> Result<(), String> {
+    if name.contains([',', '=']) {
+        return Err("name contains ',' or '='".to_string());
+    }
+    match fields.iter().find(|field| field.contains(',')) {
+        Some(field) => match field.split_once('=') {
+            Some((key, _)) => Err(format!("'{}' contains ','", key)),
+            None => Err("a field contains ','".to_string()),
+        },
+        None => Ok(()),
+    }
+}
+
+/// Members of a group: its explicit proxies, plus the matching proxies for `include-all`
+fn group_members(group: &ProxyGroup, proxy_names: &[String]) -> Result<Vec<String>, ConvertError> {
+    let mut members = group.proxies.clone().unwrap_or_default();
+    if group.include_all == Some(true) {
+        let compile = |pattern: &str| {
+            Regex::new(pattern)
+                .map_err(|e| ConvertError(format!("Invalid filter for {}: {}", group.name, e)))
+        };
+        let filter = group.filter.as_deref().map(compile).transpose()?;
+        let exclude = group.exclude_filter.as_deref().map(compile).transpose()?;
+        members.extend(
+            proxy_names
+                .iter()
+                .filter(|name| filter.as_ref().is_none_or(|re| re.is_match(name)))
+                .filter(|name| !exclude.as_ref().is_some_and(|re| re.is_match(name)))
+                .cloned(),
+        );
+    }
+    Ok(members)
+}
+
+/// Resolve group filters to explicit member lists, for formats without regex filters.
+/// Members that do not exist are dropped, and so are groups left without members.
+pub fn resolve_groups<'a>(
+    groups: &'a [ProxyGroup],
+    proxy_names: &[String],
+) -> Result<Vec<(&'a ProxyGroup, Vec<String>)>, ConvertError> {
+    let mut resolved: Vec<(&ProxyGroup, Vec<String>)> = groups
+        .iter()
+        .map(|group| Ok((group, group_members(group, proxy_names)?)))
+        .collect::<Result<_, ConvertError>>()?;
+
+    // Removing an empty group can empty a group that referenced it, so repeat until stable
+    loop {
+        let known: HashSet<&str> = proxy_names
+            .iter()
+            .map(String::as_str)
+            .chain(resolved.iter().map(|(group, _)| group.name.as_str()))
+            .chain(std::iter::once(DIRECT))
+            .collect();
+        let before = resolved.len();
+        resolved = resolved
+            .into_iter()
+            .map(|(group, members)| {
+                let members = members
+                    .into_iter()
+                    .filter(|m| known.contains(m.as_str()))
+                    .collect();
+                (group, members)
+            })
+            .filter(|(_, members): &(_, Vec<String>)| !members.is_empty())
+            .collect();
+        if resolved.len() == before {
+            break;
+        }
+    }
+
+    Ok(resolved)
+}
+
+/// A routing rule of the output config
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rule<'a> {
+    GeoSite { name: &'a str, target: &'a str },
+    GeoIp { code: &'a str, target: &'a str },
+    RuleSet { name: &'a str, target: &'a str },
+    Match { target: &'a str },
+}
+
+impl<'a> Rule<'a> {
+    /// Parse a Clash rule such as `GEOIP,CN,直接连接,no-resolve`
+    pub fn parse(rule: &'a str) -> Option<Self> {
+        let parts: Vec<&str> = rule.split(',').collect();
+        match parts.as_slice() {
+            ["GEOSITE", name, target, ..] => Some(Rule::GeoSite { name, target }),
+            ["GEOIP", code, target, ..] => Some(Rule::GeoIp { code, target }),
+            ["RULE-SET", name, target, ..] => Some(Rule::RuleSet { name, target }),
+            ["MATCH", target] => Some(Rule::Match { target }),
+            _ => None,
+        }
+    }
+}
+
+/// Rule list replacing a GEOSITE category, for `client` (`Surge`, `Loon`, `QuantumultX`)
+pub fn rule_list_url(client: &str, geosite: &str) -> Option<String> {
+    let list = match geosite.to_lowercase().as_str() {
+        "private" => "Lan",
+        "cn" => "China",
+        "apple-cn" => "Apple",
+        "steam@cn" => "SteamCN",
+        "netflix" => "Netflix",
+        "openai" => "OpenAI",
+        "telegram" => "Telegram",
+        "youtube" => "YouTube",
+        "steam" => "Steam",
+        _ => return None,
+    };
+    Some(format!(
+        "{}/{}/{}/{}.list",
+        RULE_LIST_URL, client, list, list
+    ))
+}
+
+/// Whether a GEOIP code is a country, which every client can match natively
+pub fn is_country_code(code: &str) -> bool {
+    code.len() == 2 && code.chars().all(|c| c.is_ascii_alphabetic())
+}
+
+/// Comment lines listing the dropped proxies, for formats that allow comments
+pub fn dropped_comments(dropped: &[DroppedProxy], prefix: &str) -> String {
+    dropped
+        .iter()
+        .map(|d| format!("{} Dropped {}: {}\n", prefix, d.name, d.reason))
+        .collect()
+}
+
+/// An INI-style section such as `[Proxy]`
+pub fn section(name: &str, lines: &[String]) -> String {
+    let mut section = format!("[{}]\n", name);
+    for line in lines {
+        section.push_str(line);
+        section.push('\n');
+    }
+    section
+}
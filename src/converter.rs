@@ -1,60 +1,990 @@
+use crate::backend::{Backend, DroppedProxy, Rendered};
+use crate::filter::{ProxyFilter, filter_proxies};
+use crate::loon::Loon;
+use crate::proxy::Proxy;
+use crate::quantumultx::QuantumultX;
+use crate::region::{
+    RegionMatcher, RegionRule, default_regions, parse_region_rules, region_matchers,
+};
+use crate::rename::{RenameRule, rename_proxies};
+use crate::rules::{
+    BUILTIN_POLICIES, RuleProvider, RuleSet, RuleTemplate, ServiceRoute, apply_routes,
+    parse_services,
+};
+use crate::settings::{DnsMode, DnsSettings, GeneralSettings, Profile};
+use crate::share_link::{ShareLinks, parse_link_list};
+use crate::singbox::SingBox;
+use crate::surge::Surge;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use serde_yaml::Value;
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::LazyLock;
 
 /// Represents the input Clash configuration - only extract proxies
 #[derive(Debug, Deserialize)]
 pub struct InputConfig {
-    pub proxies: Vec<Value>,
+    pub proxies: Vec<Proxy>,
 }
 
 /// Represents the output Clash configuration
 #[derive(Debug, Serialize)]
 pub struct OutputConfig {
-    pub proxies: Vec<Value>,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub general: Option<GeneralSettings>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dns: Option<DnsSettings>,
+    #[serde(rename = "proxy-providers", skip_serializing_if = "BTreeMap::is_empty")]
+    pub proxy_providers: BTreeMap<String, ProxyProvider>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub proxies: Vec<Proxy>,
     #[serde(rename = "proxy-groups")]
     pub proxy_groups: Vec<ProxyGroup>,
+    #[serde(rename = "rule-providers", skip_serializing_if = "BTreeMap::is_empty")]
+    pub rule_providers: BTreeMap<String, RuleProvider>,
     pub rules: Vec<String>,
 }
 
+/// Options controlling how proxies are grouped
+#[derive(Debug, Clone)]
+pub struct ConvertOptions {
+    /// Region table used to build the region load-balance groups
+    pub regions: Vec<RegionRule>,
+    /// Type of 全部节点负载组 and the region groups
+    pub group_type: GroupType,
+    /// Settings shared by those groups
+    pub balance: BalanceSettings,
+    /// General settings (ports, controller, ...); omitted when `None`
+    pub general: Option<GeneralSettings>,
+    /// DNS section; omitted when `None`
+    pub dns: Option<DnsSettings>,
+    /// Remote rule sets replacing the built-in GEOSITE rules; unused when empty
+    pub rule_sets: Vec<RuleSet>,
+    /// App groups with their own rules (Netflix, OpenAI, ...)
+    pub services: Vec<ServiceRoute>,
+    /// Subscription URLs referenced as `proxy-providers` instead of inlining the proxies;
+    /// unused when empty
+    pub providers: Vec<String>,
+    /// Output format
+    pub target: Target,
+    /// Keep only the proxies matching one of these filters; unused when empty
+    pub include: Vec<ProxyFilter>,
+    /// Remove the proxies matching one of these filters
+    pub exclude: Vec<ProxyFilter>,
+    /// Keep one of the proxies that share their connection parameters
+    pub dedupe: bool,
+    /// Replacements applied to proxy names in order
+    pub rename: Vec<RenameRule>,
+    /// Prefix proxy names with the flag of their region
+    pub emoji: bool,
+    /// Renumber proxies that share a name apart from its number
+    pub number: bool,
+    /// Return the conversion report instead of the config
+    pub debug: bool,
+}
+
+impl Default for ConvertOptions {
+    fn default() -> Self {
+        ConvertOptions {
+            regions: default_regions(),
+            group_type: GroupType::LoadBalance,
+            balance: BalanceSettings::default(),
+            general: None,
+            dns: None,
+            rule_sets: Vec::new(),
+            services: Vec::new(),
+            providers: Vec::new(),
+            target: Target::Clash,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            dedupe: false,
+            rename: Vec::new(),
+            emoji: false,
+            number: false,
+            debug: false,
+        }
+    }
+}
+
+impl ConvertOptions {
+    /// Build options from `/convert` query parameters
+    pub fn from_query(query: &[(String, String)]) -> Result<Self, ConvertError> {
+        let mut options = ConvertOptions::default();
+
+        if let Some(target) = query_value(query, "target") {
+            options.target = target.parse()?;
+        }
+        if options.target != Target::Clash {
+            // Options that only exist in Clash configs
+            for key in ["profile", "dns", "rules", "providers"] {
+                if query_value(query, key).is_some() {
+                    return Err(ConvertError(format!(
+                        "'{}' is only supported for target=clash",
+                        key
+                    )));
+                }
+            }
+        }
+
+        if let Some(text) = query_value(query, "regions") {
+            options.regions = parse_region_rules(text)?;
+        }
+
+        if let Some(group_type) = query_value(query, "group_type") {
+            options.group_type = group_type.parse()?;
+        }
+        if let Some(strategy) = query_value(query, "strategy") {
+            if options.group_type != GroupType::LoadBalance {
+                return Err(ConvertError(
+                    "'strategy' only applies to group_type=load-balance".to_string(),
+                ));
+            }
+            if !LOAD_BALANCE_STRATEGIES.contains(&strategy) {
+                return Err(ConvertError(format!(
+                    "Invalid strategy '{}', expected one of: {}",
+                    strategy,
+                    LOAD_BALANCE_STRATEGIES.join(", ")
+                )));
+            }
+            options.balance.strategy = Some(strategy.to_string());
+        } else if options.group_type != GroupType::LoadBalance {
+            options.balance.strategy = None;
+        }
+
+        if let Some(test_url) = query_value(query, "test_url") {
+            options.balance.url = parse_test_url(test_url)?;
+        }
+        if let Some(interval) = parse_query_number(query, "interval")? {
+            if !HEALTH_CHECK_INTERVALS.contains(&interval) {
+                return Err(ConvertError(format!(
+                    "Invalid 'interval': expected {} to {} seconds, got {}",
+                    HEALTH_CHECK_INTERVALS.start(),
+                    HEALTH_CHECK_INTERVALS.end(),
+                    interval
+                )));
+            }
+            options.balance.interval = interval;
+        }
+
+        options.balance.tolerance = parse_query_number(query, "tolerance")?;
+        if options.balance.tolerance.is_some() && options.group_type != GroupType::UrlTest {
+            return Err(ConvertError(
+                "'tolerance' only applies to group_type=url-test".to_string(),
+            ));
+        }
+        options.balance.lazy = parse_query_bool(query, "lazy")?;
+        options.balance.timeout = parse_query_number(query, "timeout")?;
+
+        if let Some(profile) = query_value(query, "profile") {
+            let profile: Profile = profile.parse()?;
+            let mut general = profile.settings();
+            general.secret = query_value(query, "secret").map(str::to_string);
+            if profile == Profile::Router && general.secret.is_none() {
+                return Err(ConvertError(
+                    "profile=router exposes the external controller to the LAN; set 'secret'"
+                        .to_string(),
+                ));
+            }
+            options.general = Some(general);
+        } else if query_value(query, "secret").is_some() {
+            return Err(ConvertError("'secret' requires a 'profile'".to_string()));
+        }
+
+        if let Some(dns) = query_value(query, "dns") {
+            options.dns = Some(dns.parse::<DnsMode>()?.settings());
+        }
+
+        if let Some(template) = query_value(query, "rules") {
+            options.rule_sets = template.parse::<RuleTemplate>()?.rule_sets();
+        }
+        if let Some(routes) = query_value(query, "routes") {
+            if options.rule_sets.is_empty() {
+                return Err(ConvertError("'routes' requires 'rules'".to_string()));
+            }
+            apply_routes(&mut options.rule_sets, routes)?;
+        }
+        if let Some(services) = query_value(query, "services") {
+            options.services = parse_services(services)?;
+        }
+        // A route to a group that is never generated is most likely a typo
+        let targets = options.route_targets();
+        if let Some(set) = options.rule_sets.iter().find(|set| {
+            !BUILTIN_POLICIES.contains(&set.target.as_str()) && !targets.contains(&set.target)
+        }) {
+            return Err(ConvertError(format!(
+                "Unknown group '{}' in route for '{}'",
+                set.target, set.name
+            )));
+        }
+
+        if parse_query_bool(query, "providers")? == Some(true) {
+            options.providers = upstream_urls(query);
+        }
+
+        options.include = query_values(query, "include")
+            .map(str::parse)
+            .collect::<Result<_, _>>()?;
+        options.exclude = query_values(query, "exclude")
+            .map(str::parse)
+            .collect::<Result<_, _>>()?;
+        options.dedupe = parse_query_bool(query, "dedupe")? == Some(true);
+        options.rename = query_values(query, "rename")
+            .map(str::parse)
+            .collect::<Result<_, _>>()?;
+        options.emoji = parse_query_bool(query, "emoji")? == Some(true);
+        options.number = parse_query_bool(query, "number")? == Some(true);
+
+        if !options.providers.is_empty() {
+            // The client fetches provider proxies from upstream, past any filtering or renaming here
+            for key in ["include", "exclude", "dedupe", "rename", "emoji", "number"] {
+                if query_value(query, key).is_some() {
+                    return Err(ConvertError(format!(
+                        "'{}' is not supported with providers",
+                        key
+                    )));
+                }
+            }
+        }
+
+        options.debug = parse_query_bool(query, "debug")? == Some(true);
+
+        Ok(options)
+    }
+}
+
+impl ConvertOptions {
+    /// Names of the proxy providers, one per subscription
+    fn provider_names(&self) -> Vec<String> {
+        (1..=self.providers.len())
+            .map(|n| format!("provider-{}", n))
+            .collect()
+    }
+
+    /// Groups a rule set can be routed to: the fixed groups, the regions and the service groups
+    fn route_targets(&self) -> Vec<String> {
+        [
+            "默认流量",
+            "节点选择",
+            "全部节点负载组",
+            OTHER_REGION_NAME,
+            "直接连接",
+        ]
+        .into_iter()
+        .map(str::to_string)
+        .chain(self.regions.iter().map(|rule| rule.name.clone()))
+        .chain(
+            self.services
+                .iter()
+                .map(|route| route.service.group_name().to_string()),
+        )
+        .collect()
+    }
+}
+
+/// Collect upstream URLs from repeated `url` parameters, each of which may hold `a|b|c`
+pub fn upstream_urls(query: &[(String, String)]) -> Vec<String> {
+    query
+        .iter()
+        .filter(|(k, _)| k == "url")
+        .flat_map(|(_, v)| {
+            v.split('|')
+                .map(str::trim)
+                .filter(|u| !u.is_empty())
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Output format of `/convert`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    /// Clash / mihomo YAML
+    Clash,
+    /// sing-box JSON
+    SingBox,
+    /// Surge profile
+    Surge,
+    /// Loon profile
+    Loon,
+    /// Quantumult X profile
+    QuantumultX,
+    /// Base64-encoded share-link list
+    Uri,
+}
+
+impl Target {
+    /// The backend rendering this format
+    pub fn backend(&self) -> &'static dyn Backend {
+        match self {
+            Target::Clash => &Clash,
+            Target::SingBox => &SingBox,
+            Target::Surge => &Surge,
+            Target::Loon => &Loon,
+            Target::QuantumultX => &QuantumultX,
+            Target::Uri => &ShareLinks,
+        }
+    }
+
+    /// MIME type of the converted config
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Target::Clash => "text/yaml; charset=utf-8",
+            Target::SingBox => "application/json; charset=utf-8",
+            Target::Surge | Target::Loon | Target::QuantumultX | Target::Uri => {
+                "text/plain; charset=utf-8"
+            }
+        }
+    }
+
+    /// File name offered for download
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            Target::Clash => "clash.yaml",
+            Target::SingBox => "singbox.json",
+            Target::Surge => "surge.conf",
+            Target::Loon => "loon.conf",
+            Target::QuantumultX => "quantumultx.conf",
+            Target::Uri => "subscription.txt",
+        }
+    }
+}
+
+impl std::str::FromStr for Target {
+    type Err = ConvertError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "clash" => Ok(Target::Clash),
+            "singbox" => Ok(Target::SingBox),
+            "surge" => Ok(Target::Surge),
+            "loon" => Ok(Target::Loon),
+            "quanx" => Ok(Target::QuantumultX),
+            "uri" => Ok(Target::Uri),
+            _ => Err(ConvertError(format!(
+                "Invalid target '{}', expected clash, singbox, surge, loon, quanx or uri",
+                s
+            ))),
+        }
+    }
+}
+
+/// Type of the balanced proxy groups
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupType {
+    LoadBalance,
+    UrlTest,
+    Fallback,
+}
+
+impl GroupType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GroupType::LoadBalance => "load-balance",
+            GroupType::UrlTest => "url-test",
+            GroupType::Fallback => "fallback",
+        }
+    }
+}
+
+impl std::str::FromStr for GroupType {
+    type Err = ConvertError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "load-balance" => Ok(GroupType::LoadBalance),
+            "url-test" => Ok(GroupType::UrlTest),
+            "fallback" => Ok(GroupType::Fallback),
+            _ => Err(ConvertError(format!(
+                "Invalid group_type '{}', expected load-balance, url-test or fallback",
+                s
+            ))),
+        }
+    }
+}
+
+/// Strategies supported by load-balance groups
+pub const LOAD_BALANCE_STRATEGIES: [&str; 3] =
+    ["consistent-hashing", "round-robin", "sticky-sessions"];
+
+/// Settings shared by the balanced groups, emitted once as the `.lb_common` anchor
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BalanceSettings {
+    pub url: String,
+    pub interval: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strategy: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tolerance: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lazy: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u32>,
+}
+
+impl Default for BalanceSettings {
+    fn default() -> Self {
+        BalanceSettings {
+            url: "http://www.gstatic.com/generate_204".to_string(),
+            interval: 180,
+            strategy: Some("consistent-hashing".to_string()),
+            tolerance: None,
+            lazy: None,
+            timeout: None,
+        }
+    }
+}
+
+/// Accepted health-check intervals in seconds
+pub const HEALTH_CHECK_INTERVALS: std::ops::RangeInclusive<u32> = 10..=86400;
+
+/// Validate a health-check URL, which must be absolute http(s)
+fn parse_test_url(value: &str) -> Result<String, ConvertError> {
+    let url =
+        url::Url::parse(value).map_err(|e| ConvertError(format!("Invalid 'test_url': {}", e)))?;
+    if !matches!(url.scheme(), "http" | "https") || url.host().is_none() {
+        return Err(ConvertError(format!(
+            "Invalid 'test_url': expected an http(s) URL, got '{}'",
+            value
+        )));
+    }
+    Ok(url.to_string())
+}
+
+/// Refresh interval of the proxy providers in seconds
+const PROVIDER_INTERVAL: u32 = 3600;
+
+/// A `proxy-providers` entry fetching a subscription
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProxyProvider {
+    #[serde(rename = "type")]
+    pub provider_type: String,
+    pub url: String,
+    pub path: String,
+    pub interval: u32,
+    #[serde(rename = "health-check")]
+    pub health_check: HealthCheck,
+}
+
+/// Health check of a proxy provider
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HealthCheck {
+    pub enable: bool,
+    pub url: String,
+    pub interval: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lazy: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u32>,
+}
+
+impl ProxyProvider {
+    /// An http provider for `url`, health-checked like the balanced groups
+    fn new(name: &str, url: &str, balance: &BalanceSettings) -> Self {
+        ProxyProvider {
+            provider_type: "http".to_string(),
+            url: url.to_string(),
+            path: format!("./proxy_providers/{}.yaml", name),
+            interval: PROVIDER_INTERVAL,
+            health_check: HealthCheck {
+                enable: true,
+                url: balance.url.clone(),
+                interval: balance.interval,
+                lazy: balance.lazy,
+                timeout: balance.timeout,
+            },
+        }
+    }
+}
+
+/// Get the first value of a query parameter
+fn query_value<'a>(query: &'a [(String, String)], key: &str) -> Option<&'a str> {
+    query
+        .iter()
+        .find(|(k, _)| k == key)
+        .map(|(_, v)| v.as_str())
+}
+
+/// Values of a parameter that may be repeated
+fn query_values<'a>(query: &'a [(String, String)], key: &str) -> impl Iterator<Item = &'a str> {
+    query
+        .iter()
+        .filter(move |(k, _)| k == key)
+        .map(|(_, v)| v.as_str())
+}
+
+fn parse_query_number<T: std::str::FromStr>(
+    query: &[(String, String)],
+    key: &str,
+) -> Result<Option<T>, ConvertError> {
+    query_value(query, key)
+        .map(|value| {
+            value.parse().map_err(|_| {
+                ConvertError(format!(
+                    "Invalid '{}': expected a number, got '{}'",
+                    key, value
+                ))
+            })
+        })
+        .transpose()
+}
+
+fn parse_query_bool(query: &[(String, String)], key: &str) -> Result<Option<bool>, ConvertError> {
+    query_value(query, key)
+        .map(|value| match value {
+            "1" | "true" => Ok(true),
+            "0" | "false" => Ok(false),
+            _ => Err(ConvertError(format!(
+                "Invalid '{}': expected true or false, got '{}'",
+                key, value
+            ))),
+        })
+        .transpose()
+}
+
+/// Name of the catch-all region group
+pub const OTHER_REGION_NAME: &str = "其他负载组";
+
 /// Represents a proxy group
-#[derive(Debug, Serialize, Clone)]
+#[derive(Debug, Serialize, Clone, Default)]
 pub struct ProxyGroup {
     pub name: String,
     #[serde(rename = "type")]
     pub group_type: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub proxies: Option<Vec<String>>,
+    #[serde(rename = "use", skip_serializing_if = "Option::is_none")]
+    pub use_providers: Option<Vec<String>>,
     #[serde(rename = "include-all", skip_serializing_if = "Option::is_none")]
     pub include_all: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub filter: Option<String>,
+    #[serde(rename = "exclude-filter", skip_serializing_if = "Option::is_none")]
+    pub exclude_filter: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub url: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub interval: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub strategy: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tolerance: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lazy: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u32>,
 }
 
+impl ProxyGroup {
+    /// The group without the settings it shares with `balance`, if it has all of them
+    fn without_balance(&self, balance: &BalanceSettings) -> Option<ProxyGroup> {
+        let shares_balance = self.url.as_ref() == Some(&balance.url)
+            && self.interval == Some(balance.interval)
+            && self.strategy == balance.strategy
+            && self.tolerance == balance.tolerance
+            && self.lazy == balance.lazy
+            && self.timeout == balance.timeout;
+
+        shares_balance.then(|| ProxyGroup {
+            url: None,
+            interval: None,
+            strategy: None,
+            tolerance: None,
+            lazy: None,
+            timeout: None,
+            ..self.clone()
+        })
+    }
+
+    /// A balanced group over every proxy matching `filter` and not `exclude_filter`
+    fn balanced(
+        name: &str,
+        filter: Option<String>,
+        exclude_filter: String,
+        options: &ConvertOptions,
+    ) -> Self {
+        let balance = &options.balance;
+        ProxyGroup {
+            name: name.to_string(),
+            group_type: options.group_type.as_str().to_string(),
+            proxies: None,
+            use_providers: (!options.providers.is_empty()).then(|| options.provider_names()),
+            include_all: options.providers.is_empty().then_some(true),
+            filter,
+            exclude_filter: Some(exclude_filter),
+            url: Some(balance.url.clone()),
+            interval: Some(balance.interval),
+            strategy: balance.strategy.clone(),
+            tolerance: balance.tolerance,
+            lazy: balance.lazy,
+            timeout: balance.timeout,
+        }
+    }
+}
+
+/// Words of info node names, as a regex group
+macro_rules! info_keyword {
+    () => {
+        "(?:剩余|流量|过期|到期|有效期|套餐|重置|官网|网址|订阅|客服|公告|expires?|expiry|traffic|remaining|reset|website)"
+    };
+}
+
+/// A date or an amount of data or days, as a regex group
+macro_rules! info_value {
+    () => {
+        r"(?:\d{4}[-/.年]\d{1,2}[-/.月]\d{1,2}|\d+(?:\.\d+)?\s*(?:[KMGT]i?B|天|days?))"
+    };
+}
+
+/// Pattern for info nodes that carry subscription details (traffic, expiry, website) instead of a server.
+/// A keyword alone is not enough, since node names mention traffic too (`2倍流量`): it has to be
+/// followed by a colon, or stand next to a date or an amount.
+pub const INFO_NODE_PATTERN: &str = concat!(
+    "(?i)剩余流量|流量重置|重置流量",
+    "|",
+    info_keyword!(),
+    r"(?:时间|日期|date|time)?\s*[:：]",
+    "|",
+    info_keyword!(),
+    r"\s*",
+    info_value!(),
+    "|",
+    info_value!(),
+    r"\s*",
+    info_keyword!(),
+);
+
+static INFO_NODE_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(INFO_NODE_PATTERN).expect("info node pattern is valid"));
+
 /// Get the proxy name from a proxy value
 pub fn get_proxy_name(proxy: &Value) -> Option<String> {
     proxy.get("name")?.as_str().map(|s| s.to_string())
 }
 
-/// Check if a region has any matching proxies
-fn has_matching_proxies(proxy_names: &[String], pattern: &str) -> bool {
-    if pattern == ".*" {
-        return true; // "其他" always included
+/// Concatenate the proxies of several subscriptions.
+/// Names that occur in more than one source are prefixed with the source number, e.g. `[2] 香港-01`.
+pub fn merge_proxies(sources: Vec<Vec<Proxy>>) -> Vec<Proxy> {
+    // Count in how many sources each name appears
+    let mut source_counts: HashMap<String, usize> = HashMap::new();
+    for proxies in &sources {
+        let names: HashSet<String> = proxies.iter().filter_map(Proxy::name).collect();
+        for name in names {
+            *source_counts.entry(name).or_default() += 1;
+        }
     }
 
-    if let Ok(re) = Regex::new(pattern) {
-        proxy_names.iter().any(|name| re.is_match(name))
-    } else {
-        false
+    let mut merged = Vec::new();
+    for (index, proxies) in sources.into_iter().enumerate() {
+        for mut proxy in proxies {
+            if let Some(name) = proxy.name()
+                && source_counts.get(&name).is_some_and(|&count| count > 1)
+            {
+                proxy.set_name(format!("[{}] {}", index + 1, name));
+            }
+            merged.push(proxy);
+        }
+    }
+
+    merged
+}
+
+/// Shadowsocks ciphers supported by mihomo
+const SS_CIPHERS: [&str; 24] = [
+    "none",
+    "aes-128-gcm",
+    "aes-192-gcm",
+    "aes-256-gcm",
+    "aes-128-ccm",
+    "aes-192-ccm",
+    "aes-256-ccm",
+    "aes-128-gcm-siv",
+    "aes-256-gcm-siv",
+    "chacha20-ietf-poly1305",
+    "xchacha20-ietf-poly1305",
+    "2022-blake3-aes-128-gcm",
+    "2022-blake3-aes-256-gcm",
+    "2022-blake3-chacha20-poly1305",
+    "aes-128-cfb",
+    "aes-192-cfb",
+    "aes-256-cfb",
+    "aes-128-ctr",
+    "aes-192-ctr",
+    "aes-256-ctr",
+    "rc4-md5",
+    "chacha20",
+    "chacha20-ietf",
+    "xchacha20",
+];
+
+/// VMess security types
+const VMESS_CIPHERS: [&str; 5] = ["auto", "none", "zero", "aes-128-gcm", "chacha20-poly1305"];
+
+/// A proxy whose name was missing or already taken
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RenamedProxy {
+    /// The upstream name, empty if there was none
+    pub from: String,
+    pub to: String,
+}
+
+/// Proxies removed or renamed during a conversion and why
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ConvertReport {
+    /// Proxies that failed validation
+    pub invalid: Vec<DroppedProxy>,
+    /// Names of the proxies removed by `include` / `exclude`
+    pub filtered: Vec<String>,
+    /// Proxies removed by `dedupe`
+    pub duplicates: Vec<DuplicateProxy>,
+    /// Valid proxies the target format cannot express
+    pub dropped: Vec<DroppedProxy>,
+    pub renamed: Vec<RenamedProxy>,
+}
+
+/// A proxy removed by `dedupe` and the proxy with the same connection parameters that was kept
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DuplicateProxy {
+    pub name: String,
+    pub kept: String,
+}
+
+/// Drop the proxies that fail validation or the `include` / `exclude` filters and, with
+/// `dedupe`, the duplicates, then rename the rest and give them unique names
+pub fn prepare_proxies(
+    proxies: Vec<Proxy>,
+    options: &ConvertOptions,
+) -> Result<(Vec<Proxy>, ConvertReport), ConvertError> {
+    let (proxies, invalid) = validate_proxies(proxies);
+    let (proxies, filtered) = filter_proxies(proxies, &options.include, &options.exclude);
+    let (mut proxies, duplicates) = match options.dedupe {
+        true => dedupe_proxies(proxies),
+        false => (proxies, Vec::new()),
+    };
+    rename_proxies(&mut proxies, options)?;
+    let renamed = resolve_names(&mut proxies);
+    let report = ConvertReport {
+        invalid,
+        filtered,
+        duplicates,
+        renamed,
+        ..Default::default()
+    };
+    Ok((proxies, report))
+}
+
+/// Connection parameters of a proxy: all of its fields except the name
+fn fingerprint(proxy: &Proxy) -> Value {
+    let mut value = Value::from(proxy.clone());
+    if let Value::Mapping(mapping) = &mut value {
+        mapping.shift_remove("name");
+        if let Some(Value::String(server)) = mapping.get_mut("server") {
+            *server = server.to_lowercase();
+        }
+    }
+    value
+}
+
+/// Keep the first of the proxies that share their connection parameters.
+/// Info nodes often share a placeholder server, so they are all kept.
+pub fn dedupe_proxies(proxies: Vec<Proxy>) -> (Vec<Proxy>, Vec<DuplicateProxy>) {
+    let mut seen: HashMap<Value, String> = HashMap::new();
+    let mut kept = Vec::new();
+    let mut duplicates = Vec::new();
+    for proxy in proxies {
+        let name = proxy.name().unwrap_or_default();
+        if !is_info_node(&name) {
+            match seen.entry(fingerprint(&proxy)) {
+                Entry::Occupied(entry) => {
+                    duplicates.push(DuplicateProxy {
+                        name,
+                        kept: entry.get().clone(),
+                    });
+                    continue;
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert(name);
+                }
+            }
+        }
+        kept.push(proxy);
+    }
+    (kept, duplicates)
+}
+
+/// A converted config and its report
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conversion {
+    pub content: String,
+    pub report: ConvertReport,
+}
+
+/// Check that Clash can load a proxy
+pub fn validate_proxy(proxy: &Proxy) -> Result<(), String> {
+    if let Some(error) = proxy.model_error() {
+        return Err(error);
+    }
+
+    // Types without a model are only checked for a type; names are resolved later
+    let Some(endpoint) = proxy.endpoint() else {
+        if !matches!(proxy, Proxy::Unknown(Value::Mapping(_))) {
+            return Err("not a mapping".to_string());
+        }
+        return match proxy.type_name().is_empty() {
+            true => Err("missing type".to_string()),
+            false => Ok(()),
+        };
+    };
+    if endpoint.server.trim().is_empty() {
+        return Err("missing server".to_string());
+    }
+    if endpoint.port == 0 {
+        return Err("invalid port 0".to_string());
+    }
+
+    let required = |value: &str, field: &str| match value.is_empty() {
+        true => Err(format!("missing {}", field)),
+        false => Ok(()),
+    };
+    match proxy {
+        Proxy::Shadowsocks(ss) => {
+            if !SS_CIPHERS.contains(&ss.cipher.as_str()) {
+                return Err(format!("unsupported cipher '{}'", ss.cipher));
+            }
+            required(&ss.password, "password")
+        }
+        Proxy::Vmess(vmess) => {
+            check_uuid(&vmess.uuid)?;
+            if let Some(cipher) = &vmess.cipher
+                && !VMESS_CIPHERS.contains(&cipher.as_str())
+            {
+                return Err(format!("unsupported cipher '{}'", cipher));
+            }
+            Ok(())
+        }
+        Proxy::Vless(vless) => check_uuid(&vless.uuid),
+        Proxy::Trojan(trojan) => required(&trojan.password, "password"),
+        Proxy::Hysteria2(hy2) => {
+            required(&hy2.password, "password")?;
+            match hy2.obfs.as_deref() {
+                None | Some("salamander") => Ok(()),
+                Some(obfs) => Err(format!("unsupported obfs '{}'", obfs)),
+            }
+        }
+        Proxy::Tuic(tuic) => match (&tuic.uuid, &tuic.password, &tuic.token) {
+            (Some(uuid), Some(_), _) => check_uuid(uuid),
+            (None, None, Some(_)) => Ok(()),
+            _ => Err("expected uuid and password (v5) or token (v4)".to_string()),
+        },
+        Proxy::WireGuard(wireguard) => {
+            required(&wireguard.private_key, "private-key")?;
+            match wireguard.ip.is_some() || wireguard.ipv6.is_some() {
+                true => Ok(()),
+                false => Err("missing ip".to_string()),
+            }
+        }
+        Proxy::Snell(snell) => required(&snell.psk, "psk"),
+        _ => Ok(()),
     }
 }
 
+/// A UUID, or a custom id of up to 30 characters that clients map to one
+fn check_uuid(uuid: &str) -> Result<(), String> {
+    let canonical = uuid.len() == 36
+        && uuid.char_indices().all(|(i, c)| match i {
+            8 | 13 | 18 | 23 => c == '-',
+            _ => c.is_ascii_hexdigit(),
+        });
+    let custom = (1..=30).contains(&uuid.chars().count());
+    match canonical || custom {
+        true => Ok(()),
+        false => Err(format!("invalid uuid '{}'", uuid)),
+    }
+}
+
+/// Split off the proxies that fail validation
+pub fn validate_proxies(proxies: Vec<Proxy>) -> (Vec<Proxy>, Vec<DroppedProxy>) {
+    let mut valid = Vec::new();
+    let mut invalid = Vec::new();
+    for proxy in proxies {
+        match validate_proxy(&proxy) {
+            Ok(()) => valid.push(proxy),
+            Err(reason) => invalid.push(DroppedProxy {
+                name: proxy.name().unwrap_or_default(),
+                reason,
+            }),
+        }
+    }
+    (valid, invalid)
+}
+
+/// Give every proxy a unique name. Proxies without one are named after their type and
+/// server; later proxies with a taken name get a suffix, e.g. `香港-01 (2)`.
+pub fn resolve_names(proxies: &mut [Proxy]) -> Vec<RenamedProxy> {
+    // Upstream names are reserved, so a suffixed name never takes one
+    let upstream: HashSet<String> = proxies
+        .iter()
+        .filter_map(Proxy::name)
+        .filter(|name| !name.trim().is_empty())
+        .collect();
+    let mut used: HashSet<String> = HashSet::new();
+    let mut renamed = Vec::new();
+
+    for proxy in proxies {
+        let from = proxy.name().unwrap_or_default();
+        let base = match from.trim().is_empty() {
+            true => match proxy.endpoint() {
+                Some(endpoint) => format!(
+                    "{} {}:{}",
+                    proxy.type_name(),
+                    endpoint.server,
+                    endpoint.port
+                ),
+                None => proxy.type_name().to_string(),
+            },
+            false => from.clone(),
+        };
+
+        let mut name = base.clone();
+        let mut suffix = 1;
+        while used.contains(&name) || (name != from && upstream.contains(&name)) {
+            suffix += 1;
+            name = format!("{} ({})", base, suffix);
+        }
+        used.insert(name.clone());
+
+        if name != from {
+            proxy.set_name(name.clone());
+            renamed.push(RenamedProxy { from, to: name });
+        }
+    }
+    renamed
+}
+
+/// Combine regexes into one that matches if any of them does
+fn join_patterns<'a>(patterns: impl IntoIterator<Item = &'a str>) -> String {
+    let patterns: Vec<&str> = patterns.into_iter().collect();
+    match patterns.as_slice() {
+        [single] => single.to_string(),
+        _ => patterns
+            .iter()
+            .map(|p| format!("(?:{})", p))
+            .collect::<Vec<_>>()
+            .join("|"),
+    }
+}
+
+/// Check if a proxy name is an info node rather than a usable proxy
+pub fn is_info_node(name: &str) -> bool {
+    INFO_NODE_REGEX.is_match(name)
+}
+
+/// Check if a region has any matching proxies
+fn has_matching_proxies(proxy_names: &[String], matcher: &RegionMatcher) -> bool {
+    proxy_names.iter().any(|name| matcher.is_match(name))
+}
+
 /// Error type for conversion
 #[derive(Debug)]
 pub struct ConvertError(pub String);
@@ -67,41 +997,101 @@ impl std::fmt::Display for ConvertError {
 
 impl std::error::Error for ConvertError {}
 
+/// Extract the proxies from subscription content.
+/// Accepts a Clash YAML config, or a plain or base64-encoded list of share links.
+pub fn parse_subscription(content: &str) -> Result<Vec<Proxy>, ConvertError> {
+    // Parse the input YAML - only extract proxies
+    let yaml_error = match serde_yaml::from_str::<InputConfig>(content) {
+        Ok(input) => return Ok(input.proxies),
+        Err(e) => e,
+    };
+
+    // Fall back to share links (ss://, vmess://, trojan://, vless://, hysteria2://)
+    let proxies = parse_link_list(content);
+    if !proxies.is_empty() {
+        return Ok(proxies.into_iter().map(Proxy::from).collect());
+    }
+
+    Err(ConvertError(format!(
+        "Failed to parse YAML: {}",
+        yaml_error
+    )))
+}
+
 /// Convert the subscription content
 pub fn convert_subscription(content: &str) -> Result<String, ConvertError> {
-    // Parse the input YAML - only extract proxies
-    let input: InputConfig = serde_yaml::from_str(content)
-        .map_err(|e| ConvertError(format!("Failed to parse YAML: {}", e)))?;
-
-    // Get all proxy names
-    let proxy_names: Vec<String> = input.proxies.iter().filter_map(get_proxy_name).collect();
-
-    // Define all possible regions
-    let all_regions = [
-        ("香港负载组", "(?i)港|hk|hongkong|hong kong"),
-        ("台湾负载组", "(?i)台|tw|taiwan"),
-        ("日本负载组", "(?i)日|jp|japan"),
-        ("新加坡负载组", "(?i)新|sg|singapore"),
-        ("美国负载组", "(?i)美|us|usa|united states|america"),
-        ("韩国负载组", "(?i)韩|kr|korea"),
-        ("英国负载组", "(?i)英|uk|britain|united kingdom"),
-        ("德国负载组", "(?i)德|de|germany"),
-        ("法国负载组", "(?i)法|fr|france"),
-        ("加拿大负载组", "(?i)加|ca|canada"),
-        ("澳大利亚负载组", "(?i)澳|au|australia"),
-        ("马来西亚负载组", "(?i)马来|my|malaysia"),
-        ("土耳其负载组", "(?i)土耳其|tr|turkey"),
-        ("阿根廷负载组", "(?i)阿根廷|ar|argentina"),
-        ("其他负载组", ".*"),
-    ];
+    convert_with_options(content, &ConvertOptions::default())
+}
+
+/// Convert the subscription content with custom options
+pub fn convert_with_options(
+    content: &str,
+    options: &ConvertOptions,
+) -> Result<String, ConvertError> {
+    let proxies = parse_subscription(content)?;
+    convert_proxies(proxies, options)
+}
+
+/// Build the output configuration from a list of proxies
+pub fn convert_proxies(
+    proxies: Vec<Proxy>,
+    options: &ConvertOptions,
+) -> Result<String, ConvertError> {
+    Ok(render_proxies(proxies, options)?.content)
+}
+
+/// Prepare the proxies, build the output configuration and render it in the target format
+pub fn render_proxies(
+    proxies: Vec<Proxy>,
+    options: &ConvertOptions,
+) -> Result<Conversion, ConvertError> {
+    let (proxies, report) = prepare_proxies(proxies, options)?;
+    let output = build_config(proxies, options)?;
+    let rendered = options.target.backend().render(&output, options)?;
+    Ok(Conversion {
+        content: rendered.content,
+        report: ConvertReport {
+            dropped: rendered.dropped,
+            ..report
+        },
+    })
+}
+
+/// Build the Clash configuration model from a list of proxies
+pub fn build_config(
+    proxies: Vec<Proxy>,
+    options: &ConvertOptions,
+) -> Result<OutputConfig, ConvertError> {
+    // Get all proxy names, keeping info nodes apart from usable proxies
+    let (info_names, proxy_names): (Vec<String>, Vec<String>) = proxies
+        .iter()
+        .filter_map(Proxy::name)
+        .partition(|name| is_info_node(name));
 
     // Filter regions that have matching proxies
-    let active_regions: Vec<(&str, &str)> = all_regions
+    let matchers = region_matchers(&options.regions)?;
+    let mut active_regions: Vec<(&str, Option<&str>, Vec<&str>)> = matchers
         .iter()
-        .filter(|(_, pattern)| has_matching_proxies(&proxy_names, pattern))
-        .map(|(name, pattern)| (*name, *pattern))
+        .filter(|matcher| has_matching_proxies(&proxy_names, matcher))
+        .map(|matcher| {
+            (
+                matcher.name.as_str(),
+                Some(matcher.filter.as_str()),
+                matcher.exclude_filter.as_deref().into_iter().collect(),
+            )
+        })
         .collect();
 
+    // "其他" with every proxy that matches no configured region, if there is one;
+    // provider proxies are only known to the client, so it is always included with providers
+    let has_other = proxy_names
+        .iter()
+        .any(|name| !matchers.iter().any(|matcher| matcher.matches_filter(name)));
+    if has_other || !options.providers.is_empty() {
+        let other_exclude = matchers.iter().map(|matcher| matcher.filter.as_str());
+        active_regions.push((OTHER_REGION_NAME, None, other_exclude.collect()));
+    }
+
     // Build proxy groups
     let mut proxy_groups: Vec<ProxyGroup> = Vec::new();
 
@@ -110,142 +1100,300 @@ pub fn convert_subscription(content: &str) -> Result<String, ConvertError> {
         vec!["节点选择".to_string(), "直接连接".to_string()];
 
     // Add all active load-balance groups
-    default_traffic_proxies.push("全部节点负载组".to_string());
-    default_traffic_proxies.extend(active_regions.iter().map(|(name, _)| name.to_string()));
+    let balanced_names: Vec<String> = std::iter::once("全部节点负载组")
+        .chain(active_regions.iter().map(|(name, _, _)| *name))
+        .map(str::to_string)
+        .collect();
+    default_traffic_proxies.extend(balanced_names.iter().cloned());
 
     proxy_groups.push(ProxyGroup {
         name: "默认流量".to_string(),
         group_type: "select".to_string(),
         proxies: Some(default_traffic_proxies),
-        include_all: None,
-        filter: None,
-        url: None,
-        interval: None,
-        strategy: None,
-    });
-
-    // 2. 节点选择 (select group with all individual proxies)
-    proxy_groups.push(ProxyGroup {
-        name: "节点选择".to_string(),
-        group_type: "select".to_string(),
-        proxies: Some(proxy_names),
-        include_all: None,
-        filter: None,
-        url: None,
-        interval: None,
-        strategy: None,
+        ..Default::default()
     });
 
-    // 3. 全部节点负载组 (load-balance group with all proxies)
-    proxy_groups.push(ProxyGroup {
-        name: "全部节点负载组".to_string(),
-        group_type: "load-balance".to_string(),
-        proxies: None,
-        include_all: Some(true),
-        filter: None,
-        url: Some("http://www.gstatic.com/generate_204".to_string()),
-        interval: Some(180),
-        strategy: Some("consistent-hashing".to_string()),
-    });
+    // Service groups (select over 默认流量, the balanced groups and 直接连接, pinned group first)
+    for route in &options.services {
+        let mut choices: Vec<String> = std::iter::once("默认流量".to_string())
+            .chain(balanced_names.iter().cloned())
+            .chain(std::iter::once("直接连接".to_string()))
+            .collect();
+        // A pinned group without proxies is not generated; keep 默认流量 first then
+        if let Some(group) = &route.group
+            && let Some(index) = choices.iter().position(|choice| choice == group)
+        {
+            let pinned = choices.remove(index);
+            choices.insert(0, pinned);
+        }
+        proxy_groups.push(ProxyGroup {
+            name: route.service.group_name().to_string(),
+            group_type: "select".to_string(),
+            proxies: Some(choices),
+            ..Default::default()
+        });
+    }
 
-    // 4. Active region load-balance groups with regex filters
-    for (name, filter) in active_regions {
+    // 2. 节点选择 (select group with all individual proxies)
+    // 3. 信息 (select group with info nodes, only when the subscription has them)
+    // With providers both take the provider proxies, split by the info node pattern
+    if options.providers.is_empty() {
         proxy_groups.push(ProxyGroup {
-            name: name.to_string(),
-            group_type: "load-balance".to_string(),
-            proxies: None,
-            include_all: Some(true),
-            filter: Some(filter.to_string()),
-            url: Some("http://www.gstatic.com/generate_204".to_string()),
-            interval: Some(180),
-            strategy: Some("consistent-hashing".to_string()),
+            name: "节点选择".to_string(),
+            group_type: "select".to_string(),
+            proxies: Some(proxy_names),
+            ..Default::default()
+        });
+        if !info_names.is_empty() {
+            proxy_groups.push(ProxyGroup {
+                name: "信息".to_string(),
+                group_type: "select".to_string(),
+                proxies: Some(info_names),
+                ..Default::default()
+            });
+        }
+    } else {
+        proxy_groups.push(ProxyGroup {
+            name: "节点选择".to_string(),
+            group_type: "select".to_string(),
+            use_providers: Some(options.provider_names()),
+            exclude_filter: Some(INFO_NODE_PATTERN.to_string()),
+            ..Default::default()
         });
+        if !info_names.is_empty() {
+            proxy_groups.push(ProxyGroup {
+                name: "信息".to_string(),
+                group_type: "select".to_string(),
+                use_providers: Some(options.provider_names()),
+                filter: Some(INFO_NODE_PATTERN.to_string()),
+                ..Default::default()
+            });
+        }
+    }
+
+    // 4. 全部节点负载组 (balanced group with all proxies except info nodes)
+    proxy_groups.push(ProxyGroup::balanced(
+        "全部节点负载组",
+        None,
+        INFO_NODE_PATTERN.to_string(),
+        options,
+    ));
+
+    // 5. Active region groups with regex filters (info nodes always excluded)
+    for (name, filter, exclude_filter) in active_regions {
+        proxy_groups.push(ProxyGroup::balanced(
+            name,
+            filter.map(str::to_string),
+            join_patterns(std::iter::once(INFO_NODE_PATTERN).chain(exclude_filter)),
+            options,
+        ));
     }
 
-    // 5. 直接连接 (select group with only DIRECT)
+    // 6. 直接连接 (select group with only DIRECT)
     proxy_groups.push(ProxyGroup {
         name: "直接连接".to_string(),
         group_type: "select".to_string(),
         proxies: Some(vec!["DIRECT".to_string()]),
-        include_all: None,
-        filter: None,
-        url: None,
-        interval: None,
-        strategy: None,
+        ..Default::default()
     });
 
-    // Build rules - GEOSITE rules, China direct, others proxy
-    let rules = vec![
-        "GEOSITE,private,直接连接".to_string(),
-        "GEOSITE,CN,直接连接".to_string(),
-        "GEOSITE,apple-cn,直接连接".to_string(),
-        "GEOSITE,steam@cn,直接连接".to_string(),
-        "GEOSITE,category-games@cn,直接连接".to_string(),
-        "GEOIP,CN,直接连接".to_string(),
-        "MATCH,默认流量".to_string(),
-    ];
+    // Build rules - GEOSITE rules or rule sets, China direct, others proxy
+    let mut rules = if options.rule_sets.is_empty() {
+        vec![
+            "GEOSITE,private,直接连接".to_string(),
+            "GEOSITE,CN,直接连接".to_string(),
+            "GEOSITE,apple-cn,直接连接".to_string(),
+            "GEOSITE,steam@cn,直接连接".to_string(),
+            "GEOSITE,category-games@cn,直接连接".to_string(),
+            "GEOIP,CN,直接连接".to_string(),
+            "MATCH,默认流量".to_string(),
+        ]
+    } else {
+        rule_set_rules(&options.rule_sets, &proxy_groups)
+    };
+
+    // Service rules go after the direct and reject rules at the top,
+    // before the first rule that resolves IPs or sends traffic to 默认流量
+    let service_position = rules
+        .iter()
+        .position(|rule| rule.starts_with("GEOIP,") || rule.ends_with(",默认流量"))
+        .unwrap_or(rules.len());
+    let service_rules = options
+        .services
+        .iter()
+        .flat_map(|route| route.service.rules());
+    rules.splice(service_position..service_position, service_rules);
+    let rule_providers = options
+        .rule_sets
+        .iter()
+        .map(|set| (set.name.clone(), set.provider()))
+        .collect();
+
+    // Reference the subscriptions instead of inlining their proxies
+    let (proxy_providers, proxies) = if options.providers.is_empty() {
+        (BTreeMap::new(), proxies)
+    } else {
+        let providers = options
+            .provider_names()
+            .into_iter()
+            .zip(&options.providers)
+            .map(|(name, url)| {
+                let provider = ProxyProvider::new(&name, url, &options.balance);
+                (name, provider)
+            })
+            .collect();
+        (providers, Vec::new())
+    };
+
+    // Flag the region groups once every reference to them is in place
+    if options.emoji {
+        flag_region_groups(&matchers, &mut proxy_groups, &mut rules);
+    }
 
     // Build output config
     let output = OutputConfig {
-        proxies: input.proxies,
+        general: options.general.clone(),
+        dns: options.dns.clone(),
+        proxy_providers,
+        proxies,
         proxy_groups,
+        rule_providers,
         rules,
     };
 
-    // Serialize to YAML
-    let mut yaml = serde_yaml::to_string(&output)
-        .map_err(|e| ConvertError(format!("Failed to serialize YAML: {}", e)))?;
-
-    // Add common load-balance config at the top with anchor
-    let lb_common = ".lb_common: &lb_common\n  url: http://www.gstatic.com/generate_204\n  interval: 180\n  strategy: consistent-hashing\n\n";
-    yaml = lb_common.to_string() + &yaml;
-
-    // Replace url/interval/strategy in all load-balance groups with merge reference
-    let lines: Vec<&str> = yaml.lines().collect();
-    let mut result_lines: Vec<String> = Vec::new();
-    let mut i = 0;
-
-    while i < lines.len() {
-        let line = lines[i];
-
-        // Check if this is a load-balance group with url config
-        if line.contains("type: load-balance") {
-            result_lines.push(line.to_string());
-            i += 1;
-
-            // Process following lines
-            while i < lines.len() {
-                let current = lines[i];
-
-                if current.contains("url: http://www.gstatic.com/generate_204") {
-                    // Found url line, get indent
-                    let indent_len = current.len() - current.trim_start().len();
-                    let indent = " ".repeat(indent_len);
-
-                    // Add merge reference instead
-                    result_lines.push(indent + "<<: *lb_common");
-
-                    // Skip the next 2 lines (interval and strategy)
-                    i += 3;
-                    break;
-                } else if current.contains("name:") || current.trim().starts_with('-') {
-                    // Reached next group, stop
-                    break;
-                } else {
-                    result_lines.push(current.to_string());
-                    i += 1;
-                }
-            }
-        } else {
-            result_lines.push(line.to_string());
-            i += 1;
+    Ok(output)
+}
+
+/// Prefix the region groups with their flag, e.g. `🇭🇰 香港负载组`, in the groups that
+/// reference them and in the rules as well
+fn flag_region_groups(
+    matchers: &[RegionMatcher],
+    proxy_groups: &mut [ProxyGroup],
+    rules: &mut [String],
+) {
+    let flagged: HashMap<&str, String> = matchers
+        .iter()
+        .filter_map(|matcher| {
+            let emoji = matcher.emoji.as_deref()?;
+            Some((matcher.name.as_str(), format!("{} {}", emoji, matcher.name)))
+        })
+        .collect();
+    let flag = |name: &mut String| {
+        if let Some(flagged) = flagged.get(name.as_str()) {
+            *name = flagged.clone();
         }
+    };
+
+    for group in proxy_groups.iter_mut() {
+        flag(&mut group.name);
+        group.proxies.iter_mut().flatten().for_each(flag);
+    }
+    for rule in rules.iter_mut() {
+        let mut fields: Vec<String> = rule.split(',').map(str::to_string).collect();
+        fields.iter_mut().for_each(flag);
+        *rule = fields.join(",");
+    }
+}
+
+/// `RULE-SET` rules followed by China direct and the catch-all.
+/// Region groups that are not generated for lack of proxies fall back to 默认流量.
+fn rule_set_rules(rule_sets: &[RuleSet], proxy_groups: &[ProxyGroup]) -> Vec<String> {
+    let mut rules: Vec<String> = rule_sets
+        .iter()
+        .map(|set| {
+            let known = BUILTIN_POLICIES.contains(&set.target.as_str())
+                || proxy_groups.iter().any(|group| group.name == set.target);
+            let target = if known {
+                set.target.as_str()
+            } else {
+                "默认流量"
+            };
+            format!("RULE-SET,{},{}", set.name, target)
+        })
+        .collect();
+    rules.push("GEOIP,CN,直接连接".to_string());
+    rules.push("MATCH,默认流量".to_string());
+    rules
+}
+
+/// Serialize only the `proxies` list, as served to proxy providers
+pub fn emit_proxy_list(proxies: Vec<Proxy>) -> Result<String, ConvertError> {
+    #[derive(Serialize)]
+    struct ProxyList {
+        proxies: Vec<Proxy>,
     }
 
-    yaml = result_lines.join("\n");
-    if !yaml.ends_with('\n') {
+    to_yaml(&ProxyList { proxies })
+}
+
+/// Clash / mihomo YAML
+pub struct Clash;
+
+impl Backend for Clash {
+    fn render(
+        &self,
+        output: &OutputConfig,
+        options: &ConvertOptions,
+    ) -> Result<Rendered, ConvertError> {
+        Ok(Rendered {
+            content: emit_yaml(output, &options.balance)?,
+            dropped: Vec::new(),
+        })
+    }
+}
+
+/// Serialize a value to YAML
+fn to_yaml<T: Serialize>(value: &T) -> Result<String, ConvertError> {
+    serde_yaml::to_string(value)
+        .map_err(|e| ConvertError(format!("Failed to serialize YAML: {}", e)))
+}
+
+/// Serialize the output config, sharing the balance settings through the `.lb_common` anchor.
+/// Groups whose settings equal `balance` get a `<<: *lb_common` merge key instead.
+fn emit_yaml(output: &OutputConfig, balance: &BalanceSettings) -> Result<String, ConvertError> {
+    let mut yaml = String::new();
+
+    // Add common balance config at the top with anchor
+    let uses_anchor = output
+        .proxy_groups
+        .iter()
+        .any(|group| group.without_balance(balance).is_some());
+    if uses_anchor {
+        yaml.push_str(".lb_common: &lb_common\n");
+        for line in to_yaml(balance)?.lines() {
+            yaml.push_str("  ");
+            yaml.push_str(line);
+            yaml.push('\n');
+        }
         yaml.push('\n');
     }
 
+    // Emit sections in field order, building proxy-groups item by item
+    let Value::Mapping(sections) = serde_yaml::to_value(output)
+        .map_err(|e| ConvertError(format!("Failed to serialize YAML: {}", e)))?
+    else {
+        return Err(ConvertError("Output is not a mapping".to_string()));
+    };
+
+    for (key, value) in sections {
+        if key.as_str() != Some("proxy-groups") {
+            let mut section = serde_yaml::Mapping::new();
+            section.insert(key, value);
+            yaml.push_str(&to_yaml(&section)?);
+            continue;
+        }
+
+        yaml.push_str("proxy-groups:\n");
+        for group in &output.proxy_groups {
+            match group.without_balance(balance) {
+                Some(stripped) => {
+                    yaml.push_str(&to_yaml(&[stripped])?);
+                    yaml.push_str("  <<: *lb_common\n");
+                }
+                None => yaml.push_str(&to_yaml(&[group])?),
+            }
+        }
+    }
+
     Ok(yaml)
 }
@@ -0,0 +1,70 @@
+use crate::converter::{ConvertError, is_info_node};
+use crate::proxy::Proxy;
+use regex::Regex;
+use std::str::FromStr;
+
+/// Field of a proxy that a filter matches
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterField {
+    Name,
+    Type,
+    Server,
+}
+
+/// A regex on the proxy name, or on its type or server when written `type:...` / `server:...`
+#[derive(Debug, Clone)]
+pub struct ProxyFilter {
+    pub field: FilterField,
+    pattern: Regex,
+}
+
+impl FromStr for ProxyFilter {
+    type Err = ConvertError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (field, pattern) = match s.split_once(':') {
+            Some(("type", pattern)) => (FilterField::Type, pattern),
+            Some(("server", pattern)) => (FilterField::Server, pattern),
+            _ => (FilterField::Name, s),
+        };
+        let pattern = Regex::new(pattern)
+            .map_err(|e| ConvertError(format!("Invalid filter '{}': {}", s, e)))?;
+        Ok(ProxyFilter { field, pattern })
+    }
+}
+
+impl ProxyFilter {
+    /// Check if the filtered field of a proxy matches
+    pub fn is_match(&self, proxy: &Proxy) -> bool {
+        let value = match self.field {
+            FilterField::Name => proxy.name(),
+            FilterField::Type => Some(proxy.type_name().to_string()),
+            FilterField::Server => proxy.server(),
+        };
+        self.pattern.is_match(&value.unwrap_or_default())
+    }
+}
+
+/// Keep the proxies that match an `include` filter (any proxy when there is none) and no
+/// `exclude` filter. Info nodes only go through `exclude`, so `include` keeps them.
+/// Returns the kept proxies and the names of the removed ones.
+pub fn filter_proxies(
+    proxies: Vec<Proxy>,
+    include: &[ProxyFilter],
+    exclude: &[ProxyFilter],
+) -> (Vec<Proxy>, Vec<String>) {
+    let mut kept = Vec::new();
+    let mut removed = Vec::new();
+    for proxy in proxies {
+        let name = proxy.name().unwrap_or_default();
+        let included = include.is_empty()
+            || is_info_node(&name)
+            || include.iter().any(|filter| filter.is_match(&proxy));
+        let excluded = exclude.iter().any(|filter| filter.is_match(&proxy));
+        match included && !excluded {
+            true => kept.push(proxy),
+            false => removed.push(name),
+        }
+    }
+    (kept, removed)
+}
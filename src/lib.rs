@@ -1,75 +1,247 @@
+pub mod backend;
 pub mod converter;
+pub mod filter;
+pub mod loon;
+pub mod proxy;
+pub mod quantumultx;
+pub mod region;
+pub mod rename;
+pub mod rules;
+pub mod settings;
+pub mod share_link;
+pub mod singbox;
+pub mod surge;
 
-use converter::convert_subscription;
+use converter::{
+    ConvertOptions, ConvertReport, emit_proxy_list, merge_proxies, parse_subscription,
+    prepare_proxies, render_proxies, upstream_urls,
+};
+use futures::future::join_all;
+use proxy::Proxy;
+use region::parse_region_rules;
 use worker::*;
 
+/// KV namespace binding holding optional configuration
+const CONFIG_KV: &str = "CONFIG";
+/// KV key and environment variable holding the region table
+const REGIONS_KEY: &str = "regions";
+const REGIONS_VAR: &str = "REGIONS";
+
+/// A fetched upstream subscription
+struct Upstream {
+    content: String,
+    user_info: Option<String>,
+    update_interval: Option<String>,
+    web_page_url: Option<String>,
+}
+
+/// Read the region table from KV, falling back to the `REGIONS` environment variable
+async fn stored_region_config(env: &Env) -> Option<String> {
+    if let Ok(kv) = env.kv(CONFIG_KV)
+        && let Ok(Some(text)) = kv.get(REGIONS_KEY).text().await
+    {
+        return Some(text);
+    }
+
+    // The variable may be a JSON/YAML string or an object defined in wrangler.toml
+    match env.object_var::<serde_json::Value>(REGIONS_VAR).ok()? {
+        serde_json::Value::String(text) => Some(text),
+        value => Some(value.to_string()),
+    }
+}
+
+/// Fetch one upstream subscription, keeping the subscription info headers
+async fn fetch_upstream(url: Url) -> std::result::Result<Upstream, String> {
+    let mut resp = Fetch::Url(url.clone())
+        .send()
+        .await
+        .map_err(|e| format!("Fetch failed for {}: {}", url, e))?;
+
+    // Extract headers before consuming body
+    let user_info = resp.headers().get("subscription-userinfo").ok().flatten();
+    let update_interval = resp.headers().get("profile-update-interval").ok().flatten();
+    let web_page_url = resp.headers().get("profile-web-page-url").ok().flatten();
+
+    let content = resp
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read response from {}: {}", url, e))?;
+
+    Ok(Upstream {
+        content,
+        user_info,
+        update_interval,
+        web_page_url,
+    })
+}
+
+/// A request's options and the merged proxies of its upstreams
+struct Subscription {
+    options: ConvertOptions,
+    proxies: Vec<Proxy>,
+    upstreams: Vec<Upstream>,
+}
+
+/// Parse the options of a request and fetch, parse and merge its upstreams.
+/// Errors are returned as the response to send.
+async fn load_subscription(
+    req: &Request,
+    env: &Env,
+) -> Result<std::result::Result<Subscription, Response>> {
+    let url = req.url()?;
+    let query: Vec<(String, String)> = url
+        .query_pairs()
+        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+        .collect();
+
+    let target_urls = upstream_urls(&query);
+    if target_urls.is_empty() {
+        return Response::error("Missing 'url' parameter", 400).map(Err);
+    }
+
+    let mut parsed_urls: Vec<Url> = Vec::new();
+    for target_url in &target_urls {
+        match target_url.parse() {
+            Ok(u) => parsed_urls.push(u),
+            Err(e) => {
+                return Response::error(format!("Invalid URL: {}", e), 400).map(Err);
+            }
+        }
+    }
+
+    let mut options = match ConvertOptions::from_query(&query) {
+        Ok(options) => options,
+        Err(e) => return Response::error(e.to_string(), 400).map(Err),
+    };
+
+    // Region table: query parameter, then KV / environment, then the built-in default
+    if !query.iter().any(|(k, _)| k == "regions")
+        && let Some(text) = stored_region_config(env).await
+    {
+        match parse_region_rules(&text) {
+            Ok(regions) => options.regions = regions,
+            Err(e) => {
+                return Response::error(format!("Invalid region configuration: {}", e), 500)
+                    .map(Err);
+            }
+        }
+    }
+
+    // Fetch all upstreams concurrently
+    let mut upstreams = Vec::new();
+    for result in join_all(parsed_urls.into_iter().map(fetch_upstream)).await {
+        match result {
+            Ok(upstream) => upstreams.push(upstream),
+            Err(e) => return Response::error(e, 500).map(Err),
+        }
+    }
+
+    let mut sources = Vec::new();
+    for upstream in &upstreams {
+        match parse_subscription(&upstream.content) {
+            Ok(proxies) => sources.push(proxies),
+            Err(e) => {
+                return Response::error(format!("Conversion failed: {}", e), 500).map(Err);
+            }
+        }
+    }
+
+    Ok(Ok(Subscription {
+        options,
+        proxies: merge_proxies(sources),
+        upstreams,
+    }))
+}
+
+/// Response headers for a config download, with the subscription info headers
+/// forwarded from the first upstream that has them
+fn download_headers(upstreams: &[Upstream], content_type: &str, filename: &str) -> Result<Headers> {
+    let headers = Headers::new();
+    headers.set("Content-Type", content_type)?;
+    headers.set(
+        "Content-Disposition",
+        &format!("attachment; filename={}", filename),
+    )?;
+
+    let user_info = upstreams.iter().find_map(|u| u.user_info.as_ref());
+    let update_interval = upstreams.iter().find_map(|u| u.update_interval.as_ref());
+    let web_page_url = upstreams.iter().find_map(|u| u.web_page_url.as_ref());
+
+    if let Some(val) = user_info {
+        headers.set("subscription-userinfo", val)?;
+    }
+    if let Some(val) = update_interval {
+        headers.set("profile-update-interval", val)?;
+    }
+    if let Some(val) = web_page_url {
+        headers.set("profile-web-page-url", val)?;
+    }
+
+    Ok(headers)
+}
+
+/// Set the counts of removed proxies as response headers
+fn report_headers(headers: &Headers, report: &ConvertReport) -> Result<()> {
+    headers.set("x-invalid-proxies", &report.invalid.len().to_string())?;
+    headers.set("x-duplicate-proxies", &report.duplicates.len().to_string())?;
+    headers.set("x-dropped-proxies", &report.dropped.len().to_string())
+}
+
 #[event(fetch)]
 pub async fn main(req: Request, env: Env, _ctx: Context) -> Result<Response> {
     let router = Router::new();
 
     router
-        .get_async("/convert", |req, _ctx| async move {
-            let url = req.url()?;
-            let params: std::collections::HashMap<String, String> = url
-                .query_pairs()
-                .map(|(k, v)| (k.into_owned(), v.into_owned()))
-                .collect();
-
-            let target_url = match params.get("url") {
-                Some(u) => u,
-                None => {
-                    return Response::error("Missing 'url' parameter", 400);
-                }
+        .get_async("/convert", |req, ctx| async move {
+            let subscription = match load_subscription(&req, &ctx.env).await? {
+                Ok(subscription) => subscription,
+                Err(response) => return Ok(response),
             };
 
-            let parsed_url: Url = match target_url.parse() {
-                Ok(u) => u,
-                Err(e) => {
-                    return Response::error(format!("Invalid URL: {}", e), 400);
+            // Convert the subscription
+            match render_proxies(subscription.proxies, &subscription.options) {
+                Ok(conversion) => {
+                    if subscription.options.debug {
+                        return Response::from_json(&conversion.report);
+                    }
+                    let target = subscription.options.target;
+                    let headers = download_headers(
+                        &subscription.upstreams,
+                        target.content_type(),
+                        target.file_name(),
+                    )?;
+                    report_headers(&headers, &conversion.report)?;
+                    Ok(Response::ok(conversion.content)?.with_headers(headers))
                 }
+                Err(e) => Response::error(format!("Conversion failed: {}", e), 500),
+            }
+        })
+        .get_async("/proxies", |req, ctx| async move {
+            let subscription = match load_subscription(&req, &ctx.env).await? {
+                Ok(subscription) => subscription,
+                Err(response) => return Ok(response),
             };
 
-            let response = Fetch::Url(parsed_url).send().await;
-            match response {
-                Ok(mut resp) => {
-                    // Extract headers before consuming body
-                    let user_info = resp.headers().get("subscription-userinfo").ok().flatten();
-                    let update_interval =
-                        resp.headers().get("profile-update-interval").ok().flatten();
-                    let web_page_url = resp.headers().get("profile-web-page-url").ok().flatten();
-
-                    match resp.text().await {
-                        Ok(content) => {
-                            // Convert the subscription
-                            match convert_subscription(&content) {
-                                Ok(converted) => {
-                                    let headers = Headers::new();
-                                    headers.set("Content-Type", "text/yaml; charset=utf-8")?;
-                                    headers.set(
-                                        "Content-Disposition",
-                                        "attachment; filename=clash.yaml",
-                                    )?;
-
-                                    // Forward subscription info headers
-                                    if let Some(val) = user_info {
-                                        headers.set("subscription-userinfo", &val)?;
-                                    }
-                                    if let Some(val) = update_interval {
-                                        headers.set("profile-update-interval", &val)?;
-                                    }
-                                    if let Some(val) = web_page_url {
-                                        headers.set("profile-web-page-url", &val)?;
-                                    }
-
-                                    Ok(Response::ok(converted)?.with_headers(headers))
-                                }
-                                Err(e) => Response::error(format!("Conversion failed: {}", e), 500),
-                            }
-                        }
-                        Err(e) => Response::error(format!("Failed to read response: {}", e), 500),
-                    }
+            // Only the valid proxies, for use as a proxy provider
+            let (proxies, report) =
+                match prepare_proxies(subscription.proxies, &subscription.options) {
+                    Ok(prepared) => prepared,
+                    Err(e) => return Response::error(format!("Conversion failed: {}", e), 500),
+                };
+            if subscription.options.debug {
+                return Response::from_json(&report);
+            }
+            match emit_proxy_list(proxies) {
+                Ok(list) => {
+                    let headers = download_headers(
+                        &subscription.upstreams,
+                        "text/yaml; charset=utf-8",
+                        "proxies.yaml",
+                    )?;
+                    report_headers(&headers, &report)?;
+                    Ok(Response::ok(list)?.with_headers(headers))
                 }
-                Err(e) => Response::error(format!("Fetch failed: {}", e), 500),
+                Err(e) => Response::error(format!("Conversion failed: {}", e), 500),
             }
         })
         .run(req, env)
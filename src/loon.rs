@@ -0,0 +1,234 @@
+use crate::backend::{
+    Backend, Rendered, Rule, check_line, convert_each, dropped_comments, is_country_code,
+    resolve_groups, rule_list_url, section, unsupported,
+};
+use crate::converter::{ConvertError, ConvertOptions, OutputConfig, ProxyGroup};
+use crate::proxy::{Endpoint, Proxy, Tls, Transport};
+
+/// Loon profile with `[Proxy]`, `[Proxy Group]`, `[Remote Rule]` and `[Rule]`
+pub struct Loon;
+
+impl Backend for Loon {
+    fn render(
+        &self,
+        output: &OutputConfig,
+        _options: &ConvertOptions,
+    ) -> Result<Rendered, ConvertError> {
+        let (proxies, dropped) = convert_each(&output.proxies, proxy_line);
+        let proxy_names: Vec<String> = proxies.iter().map(|(name, _)| name.clone()).collect();
+        let groups = resolve_groups(&output.proxy_groups, &proxy_names)?;
+
+        let proxy_lines: Vec<String> = proxies.into_iter().map(|(_, line)| line).collect();
+        let group_lines: Vec<String> = groups
+            .iter()
+            .map(|(group, members)| group_line(group, members))
+            .collect();
+
+        // GEOSITE categories become remote rule lists, the rest stays local
+        let mut remote_rules = Vec::new();
+        let mut local_rules = Vec::new();
+        for rule in &output.rules {
+            match Rule::parse(rule) {
+                Some(Rule::GeoSite { name, target }) => {
+                    if let Some(url) = rule_list_url("Loon", name) {
+                        remote_rules.push(format!(
+                            "{}, policy={}, tag={}, enabled=true",
+                            url, target, name
+                        ));
+                    }
+                }
+                Some(Rule::GeoIp { code, target }) if is_country_code(code) => {
+                    local_rules.push(format!("GEOIP,{},{}", code.to_uppercase(), target));
+                }
+                Some(Rule::Match { target }) => local_rules.push(format!("FINAL,{}", target)),
+                _ => {}
+            }
+        }
+
+        let content = [
+            dropped_comments(&dropped, "#"),
+            section("Proxy", &proxy_lines),
+            section("Proxy Group", &group_lines),
+            section("Remote Rule", &remote_rules),
+            section("Rule", &local_rules),
+        ]
+        .into_iter()
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n");
+        Ok(Rendered { content, dropped })
+    }
+}
+
+/// A `[Proxy]` line, or the reason the proxy is not supported
+fn proxy_line(proxy: &Proxy) -> Result<String, String> {
+    let quoted = |value: &str| format!("\"{}\"", value);
+    let positional = |kind: &str, endpoint: &Endpoint| {
+        vec![
+            kind.to_string(),
+            endpoint.server.clone(),
+            endpoint.port.to_string(),
+        ]
+    };
+
+    // Type, server, port and the positional fields of each type, then key=value options
+    let (mut fields, endpoint, udp) = match proxy {
+        Proxy::Shadowsocks(ss) => {
+            let mut fields = positional("Shadowsocks", &ss.endpoint);
+            fields.push(ss.cipher.clone());
+            fields.push(quoted(&ss.password));
+            match ss.plugin.as_deref() {
+                None => {}
+                Some("obfs") => {
+                    let opts = ss.plugin_opts.clone().unwrap_or_default();
+                    fields.push(format!(
+                        "obfs-name={}",
+                        opts.mode.as_deref().unwrap_or("http")
+                    ));
+                    if let Some(host) = &opts.host {
+                        fields.push(format!("obfs-host={}", host));
+                    }
+                }
+                Some(plugin) => return Err(format!("unsupported plugin '{}'", plugin)),
+            }
+            (fields, &ss.endpoint, ss.udp)
+        }
+        Proxy::Vmess(vmess) => {
+            let mut fields = positional("vmess", &vmess.endpoint);
+            fields.push(vmess.cipher.clone().unwrap_or("auto".to_string()));
+            fields.push(quoted(&vmess.uuid));
+            push_transport(&vmess.transport, &mut fields)?;
+            fields.push(format!("alterId={}", vmess.alter_id.unwrap_or(0)));
+            if vmess.tls.enabled() {
+                fields.push("over-tls=true".to_string());
+                push_tls(&vmess.tls, &mut fields);
+            }
+            (fields, &vmess.endpoint, vmess.udp)
+        }
+        Proxy::Vless(vless) => {
+            let mut fields = positional("VLESS", &vless.endpoint);
+            fields.push(quoted(&vless.uuid));
+            push_transport(&vless.transport, &mut fields)?;
+            if let Some(flow) = &vless.flow {
+                fields.push(format!("flow={}", flow));
+            }
+            if vless.tls_enabled() {
+                fields.push("over-tls=true".to_string());
+                push_tls(&vless.tls, &mut fields);
+            }
+            if let Some(reality) = &vless.reality_opts {
+                if let Some(key) = &reality.public_key {
+                    fields.push(format!("public-key={}", quoted(key)));
+                }
+                if let Some(short_id) = &reality.short_id {
+                    fields.push(format!("short-id={}", short_id));
+                }
+            }
+            (fields, &vless.endpoint, vless.udp)
+        }
+        Proxy::Trojan(trojan) => {
+            let mut fields = positional("trojan", &trojan.endpoint);
+            fields.push(quoted(&trojan.password));
+            push_transport(&trojan.transport, &mut fields)?;
+            push_tls(&trojan.tls, &mut fields);
+            (fields, &trojan.endpoint, trojan.udp)
+        }
+        Proxy::Hysteria2(hy2) => {
+            if hy2.obfs.is_some() {
+                return Err("obfs is not supported".to_string());
+            }
+            let mut fields = positional("Hysteria2", &hy2.endpoint);
+            fields.push(quoted(&hy2.password));
+            push_tls(&hy2.tls, &mut fields);
+            if let Some(down) = hy2.down_mbps() {
+                fields.push(format!("download-bandwidth={}", down));
+            }
+            (fields, &hy2.endpoint, hy2.udp)
+        }
+        Proxy::Http(http) => {
+            let kind = if http.tls.enabled() { "https" } else { "http" };
+            let mut fields = positional(kind, &http.endpoint);
+            if let Some(username) = &http.username {
+                fields.push(username.clone());
+                fields.push(quoted(http.password.as_deref().unwrap_or_default()));
+            }
+            (fields, &http.endpoint, None)
+        }
+        Proxy::Socks5(socks) => {
+            if socks.tls.enabled() {
+                return Err("socks5 over TLS is not supported".to_string());
+            }
+            let mut fields = positional("socks5", &socks.endpoint);
+            if let Some(username) = &socks.username {
+                fields.push(username.clone());
+                fields.push(quoted(socks.password.as_deref().unwrap_or_default()));
+            }
+            (fields, &socks.endpoint, socks.udp)
+        }
+        _ => return Err(unsupported(proxy)),
+    };
+
+    if udp == Some(true) {
+        fields.push("udp=true".to_string());
+    }
+    check_line(&endpoint.name, &fields)?;
+    Ok(format!("{} = {}", endpoint.name, fields.join(",")))
+}
+
+/// TLS options
+fn push_tls(tls: &Tls, fields: &mut Vec<String>) {
+    if let Some(sni) = tls.server_name() {
+        fields.push(format!("sni={}", sni));
+    }
+    if tls.insecure() {
+        fields.push("skip-cert-verify=true".to_string());
+    }
+}
+
+/// Transport options; Loon supports tcp and WebSocket
+fn push_transport(transport: &Transport, fields: &mut Vec<String>) -> Result<(), String> {
+    match transport.network() {
+        "tcp" => {
+            fields.push("transport=tcp".to_string());
+            Ok(())
+        }
+        "ws" => {
+            let ws = transport.ws_opts.clone().unwrap_or_default();
+            fields.push("transport=ws".to_string());
+            if let Some(path) = &ws.path {
+                fields.push(format!("path={}", path));
+            }
+            if let Some(host) = ws.host() {
+                fields.push(format!("host={}", host));
+            }
+            Ok(())
+        }
+        network => Err(format!("unsupported transport '{}'", network)),
+    }
+}
+
+/// A `[Proxy Group]` line
+fn group_line(group: &ProxyGroup, members: &[String]) -> String {
+    let mut fields = vec![group.group_type.clone()];
+    fields.extend(members.iter().cloned());
+    if group.group_type != "select" {
+        if let Some(url) = &group.url {
+            fields.push(format!("url={}", url));
+        }
+        if let Some(interval) = group.interval {
+            fields.push(format!("interval={}", interval));
+        }
+        if let Some(tolerance) = group.tolerance {
+            fields.push(format!("tolerance={}", tolerance));
+        }
+        if group.group_type == "load-balance" {
+            // pcc keeps a destination on the same proxy, like consistent-hashing
+            let algorithm = match group.strategy.as_deref() {
+                Some("round-robin") => "round-robin",
+                _ => "pcc",
+            };
+            fields.push(format!("algorithm={}", algorithm));
+        }
+    }
+    format!("{} = {}", group.name, fields.join(","))
+}
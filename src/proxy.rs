@@ -0,0 +1,796 @@
+use crate::converter::get_proxy_name;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_yaml::{Mapping, Value};
+use std::collections::BTreeMap;
+
+/// Clash types with a typed model; other types are kept as [`Proxy::Unknown`]
+pub const TYPED_PROXY_TYPES: [&str; 10] = [
+    "ss",
+    "vmess",
+    "trojan",
+    "vless",
+    "hysteria2",
+    "tuic",
+    "wireguard",
+    "http",
+    "socks5",
+    "snell",
+];
+
+/// A proxy of a Clash subscription.
+/// Keys the model does not know are kept in `extra` and written back unchanged.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "Value", into = "Value")]
+pub enum Proxy {
+    Shadowsocks(Shadowsocks),
+    Vmess(Vmess),
+    Trojan(Trojan),
+    Vless(Vless),
+    Hysteria2(Hysteria2),
+    Tuic(Tuic),
+    WireGuard(WireGuard),
+    Http(Http),
+    Socks5(Socks5),
+    Snell(Snell),
+    /// Any other type, or a proxy whose fields do not fit its type, kept as it is
+    Unknown(Value),
+}
+
+impl Proxy {
+    /// Clash `type` of the proxy
+    pub fn type_name(&self) -> &str {
+        match self {
+            Proxy::Shadowsocks(_) => "ss",
+            Proxy::Vmess(_) => "vmess",
+            Proxy::Trojan(_) => "trojan",
+            Proxy::Vless(_) => "vless",
+            Proxy::Hysteria2(_) => "hysteria2",
+            Proxy::Tuic(_) => "tuic",
+            Proxy::WireGuard(_) => "wireguard",
+            Proxy::Http(_) => "http",
+            Proxy::Socks5(_) => "socks5",
+            Proxy::Snell(_) => "snell",
+            Proxy::Unknown(value) => value.get("type").and_then(Value::as_str).unwrap_or(""),
+        }
+    }
+
+    /// Name, server and port, for every proxy but [`Proxy::Unknown`]
+    pub fn endpoint(&self) -> Option<&Endpoint> {
+        match self {
+            Proxy::Shadowsocks(p) => Some(&p.endpoint),
+            Proxy::Vmess(p) => Some(&p.endpoint),
+            Proxy::Trojan(p) => Some(&p.endpoint),
+            Proxy::Vless(p) => Some(&p.endpoint),
+            Proxy::Hysteria2(p) => Some(&p.endpoint),
+            Proxy::Tuic(p) => Some(&p.endpoint),
+            Proxy::WireGuard(p) => Some(&p.endpoint),
+            Proxy::Http(p) => Some(&p.endpoint),
+            Proxy::Socks5(p) => Some(&p.endpoint),
+            Proxy::Snell(p) => Some(&p.endpoint),
+            Proxy::Unknown(_) => None,
+        }
+    }
+
+    fn endpoint_mut(&mut self) -> Option<&mut Endpoint> {
+        match self {
+            Proxy::Shadowsocks(p) => Some(&mut p.endpoint),
+            Proxy::Vmess(p) => Some(&mut p.endpoint),
+            Proxy::Trojan(p) => Some(&mut p.endpoint),
+            Proxy::Vless(p) => Some(&mut p.endpoint),
+            Proxy::Hysteria2(p) => Some(&mut p.endpoint),
+            Proxy::Tuic(p) => Some(&mut p.endpoint),
+            Proxy::WireGuard(p) => Some(&mut p.endpoint),
+            Proxy::Http(p) => Some(&mut p.endpoint),
+            Proxy::Socks5(p) => Some(&mut p.endpoint),
+            Proxy::Snell(p) => Some(&mut p.endpoint),
+            Proxy::Unknown(_) => None,
+        }
+    }
+
+    /// The proxy name, if it has one
+    pub fn name(&self) -> Option<String> {
+        match self {
+            Proxy::Unknown(value) => get_proxy_name(value),
+            _ => self.endpoint().map(|endpoint| endpoint.name.clone()),
+        }
+    }
+
+    /// The server address, if it has one
+    pub fn server(&self) -> Option<String> {
+        match self {
+            Proxy::Unknown(value) => value.get("server")?.as_str().map(str::to_string),
+            _ => self.endpoint().map(|endpoint| endpoint.server.clone()),
+        }
+    }
+
+    /// Why a proxy of a typed Clash type did not fit its model
+    pub fn model_error(&self) -> Option<String> {
+        match self {
+            Proxy::Unknown(value) => typed(value)?.err().map(|e| e.to_string()),
+            _ => None,
+        }
+    }
+
+    pub fn set_name(&mut self, name: String) {
+        match self {
+            Proxy::Unknown(Value::Mapping(mapping)) => {
+                mapping.insert(Value::String("name".to_string()), Value::String(name));
+            }
+            _ => {
+                if let Some(endpoint) = self.endpoint_mut() {
+                    endpoint.name = name;
+                }
+            }
+        }
+    }
+}
+
+impl From<Value> for Proxy {
+    fn from(value: Value) -> Self {
+        match typed(&value) {
+            Some(Ok(proxy)) => proxy,
+            _ => Proxy::Unknown(value),
+        }
+    }
+}
+
+/// Read a proxy of a typed Clash type into its model; `None` for other types
+fn typed(value: &Value) -> Option<Result<Proxy, serde_yaml::Error>> {
+    fn read<T: DeserializeOwned>(
+        fields: Value,
+        variant: fn(T) -> Proxy,
+    ) -> Result<Proxy, serde_yaml::Error> {
+        serde_yaml::from_value(fields).map(variant)
+    }
+
+    // The variant carries the type, so it is not part of the fields
+    let mut fields = value.clone();
+    if let Value::Mapping(mapping) = &mut fields {
+        mapping.shift_remove("type");
+    }
+    let proxy = match value.get("type").and_then(Value::as_str)? {
+        "ss" => read(fields, Proxy::Shadowsocks),
+        "vmess" => read(fields, Proxy::Vmess),
+        "trojan" => read(fields, Proxy::Trojan),
+        "vless" => read(fields, Proxy::Vless),
+        "hysteria2" => read(fields, Proxy::Hysteria2),
+        "tuic" => read(fields, Proxy::Tuic),
+        "wireguard" => read(fields, Proxy::WireGuard),
+        "http" => read(fields, Proxy::Http),
+        "socks5" => read(fields, Proxy::Socks5),
+        "snell" => read(fields, Proxy::Snell),
+        _ => return None,
+    };
+    Some(proxy)
+}
+
+impl From<Proxy> for Value {
+    fn from(proxy: Proxy) -> Self {
+        let kind = proxy.type_name().to_string();
+        let fields = match proxy {
+            Proxy::Shadowsocks(p) => serde_yaml::to_value(p),
+            Proxy::Vmess(p) => serde_yaml::to_value(p),
+            Proxy::Trojan(p) => serde_yaml::to_value(p),
+            Proxy::Vless(p) => serde_yaml::to_value(p),
+            Proxy::Hysteria2(p) => serde_yaml::to_value(p),
+            Proxy::Tuic(p) => serde_yaml::to_value(p),
+            Proxy::WireGuard(p) => serde_yaml::to_value(p),
+            Proxy::Http(p) => serde_yaml::to_value(p),
+            Proxy::Socks5(p) => serde_yaml::to_value(p),
+            Proxy::Snell(p) => serde_yaml::to_value(p),
+            Proxy::Unknown(value) => return value,
+        };
+        let Ok(Value::Mapping(mut fields)) = fields else {
+            return Value::Null;
+        };
+
+        // Name and type first, like in subscriptions
+        let mut mapping = Mapping::new();
+        if let Some(name) = fields.shift_remove("name") {
+            mapping.insert(Value::String("name".to_string()), name);
+        }
+        mapping.insert(Value::String("type".to_string()), Value::String(kind));
+        mapping.extend(fields);
+        Value::Mapping(mapping)
+    }
+}
+
+/// Fields every proxy has
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Endpoint {
+    /// Empty if the upstream gave none, until names are resolved
+    #[serde(default, deserialize_with = "name")]
+    pub name: String,
+    #[serde(deserialize_with = "text")]
+    pub server: String,
+    #[serde(deserialize_with = "number")]
+    pub port: u16,
+}
+
+/// TLS settings shared by the protocols that use TLS
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Tls {
+    /// Only for protocols where TLS is optional
+    #[serde(
+        default,
+        deserialize_with = "optional_bool",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub tls: Option<bool>,
+    /// Server name of vmess and vless
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub servername: Option<String>,
+    /// Server name of the other protocols
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sni: Option<String>,
+    #[serde(
+        default,
+        deserialize_with = "optional_bool",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub skip_cert_verify: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alpn: Option<OneOrMany>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_fingerprint: Option<String>,
+    /// Certificate fingerprint
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fingerprint: Option<String>,
+}
+
+impl Tls {
+    pub fn enabled(&self) -> bool {
+        self.tls == Some(true)
+    }
+
+    /// `servername` or `sni`, whichever is set
+    pub fn server_name(&self) -> Option<&str> {
+        self.servername.as_deref().or(self.sni.as_deref())
+    }
+
+    pub fn insecure(&self) -> bool {
+        self.skip_cert_verify == Some(true)
+    }
+
+    pub fn alpn(&self) -> Vec<String> {
+        self.alpn
+            .as_ref()
+            .map(OneOrMany::to_vec)
+            .unwrap_or_default()
+    }
+}
+
+/// V2Ray transport of vmess, vless and trojan
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Transport {
+    /// `tcp` (default), `ws`, `grpc`, `h2` or `http`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub network: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ws_opts: Option<WsOpts>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub grpc_opts: Option<GrpcOpts>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub h2_opts: Option<H2Opts>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub http_opts: Option<HttpOpts>,
+}
+
+impl Transport {
+    pub fn network(&self) -> &str {
+        self.network.as_deref().unwrap_or("tcp")
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WsOpts {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub headers: Option<BTreeMap<String, String>>,
+    #[serde(flatten)]
+    pub extra: Mapping,
+}
+
+impl WsOpts {
+    pub fn host(&self) -> Option<&str> {
+        self.headers.as_ref()?.get("Host").map(String::as_str)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct GrpcOpts {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub grpc_service_name: Option<String>,
+    #[serde(flatten)]
+    pub extra: Mapping,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct H2Opts {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<OneOrMany>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(flatten)]
+    pub extra: Mapping,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HttpOpts {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<OneOrMany>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub headers: Option<BTreeMap<String, OneOrMany>>,
+    #[serde(flatten)]
+    pub extra: Mapping,
+}
+
+impl HttpOpts {
+    pub fn hosts(&self) -> Vec<String> {
+        self.headers
+            .as_ref()
+            .and_then(|headers| headers.get("Host"))
+            .map(OneOrMany::to_vec)
+            .unwrap_or_default()
+    }
+}
+
+/// A list that may also be written as a single string
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum OneOrMany {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl OneOrMany {
+    pub fn to_vec(&self) -> Vec<String> {
+        match self {
+            OneOrMany::One(item) => vec![item.clone()],
+            OneOrMany::Many(items) => items.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Shadowsocks {
+    #[serde(flatten)]
+    pub endpoint: Endpoint,
+    #[serde(deserialize_with = "text")]
+    pub cipher: String,
+    #[serde(deserialize_with = "text")]
+    pub password: String,
+    #[serde(
+        default,
+        deserialize_with = "optional_bool",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub udp: Option<bool>,
+    /// `obfs` or `v2ray-plugin`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub plugin: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub plugin_opts: Option<PluginOpts>,
+    #[serde(flatten)]
+    pub extra: Mapping,
+}
+
+/// Options of the `obfs` and `v2ray-plugin` plugins, and of snell `obfs-opts`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PluginOpts {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(
+        default,
+        deserialize_with = "optional_bool",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub tls: Option<bool>,
+    #[serde(
+        default,
+        deserialize_with = "optional_bool",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub mux: Option<bool>,
+    #[serde(flatten)]
+    pub extra: Mapping,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Vmess {
+    #[serde(flatten)]
+    pub endpoint: Endpoint,
+    #[serde(deserialize_with = "text")]
+    pub uuid: String,
+    #[serde(
+        rename = "alterId",
+        default,
+        deserialize_with = "optional_number",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub alter_id: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cipher: Option<String>,
+    #[serde(
+        default,
+        deserialize_with = "optional_bool",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub udp: Option<bool>,
+    #[serde(flatten)]
+    pub tls: Tls,
+    #[serde(flatten)]
+    pub transport: Transport,
+    #[serde(flatten)]
+    pub extra: Mapping,
+}
+
+impl Vmess {
+    /// AEAD header, used when alterId is 0
+    pub fn aead(&self) -> bool {
+        self.alter_id.unwrap_or(0) == 0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Trojan {
+    #[serde(flatten)]
+    pub endpoint: Endpoint,
+    #[serde(deserialize_with = "text")]
+    pub password: String,
+    #[serde(
+        default,
+        deserialize_with = "optional_bool",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub udp: Option<bool>,
+    #[serde(flatten)]
+    pub tls: Tls,
+    #[serde(flatten)]
+    pub transport: Transport,
+    #[serde(flatten)]
+    pub extra: Mapping,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Vless {
+    #[serde(flatten)]
+    pub endpoint: Endpoint,
+    #[serde(deserialize_with = "text")]
+    pub uuid: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub flow: Option<String>,
+    #[serde(
+        default,
+        deserialize_with = "optional_bool",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub udp: Option<bool>,
+    #[serde(flatten)]
+    pub tls: Tls,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reality_opts: Option<RealityOpts>,
+    #[serde(flatten)]
+    pub transport: Transport,
+    #[serde(flatten)]
+    pub extra: Mapping,
+}
+
+impl Vless {
+    /// TLS is on with `tls: true` and always with REALITY
+    pub fn tls_enabled(&self) -> bool {
+        self.tls.enabled() || self.reality_opts.is_some()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct RealityOpts {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
+    #[serde(
+        default,
+        deserialize_with = "optional_text",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub short_id: Option<String>,
+    #[serde(flatten)]
+    pub extra: Mapping,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Hysteria2 {
+    #[serde(flatten)]
+    pub endpoint: Endpoint,
+    #[serde(deserialize_with = "text")]
+    pub password: String,
+    /// Bandwidth such as `100` or `"100 Mbps"`
+    #[serde(
+        default,
+        deserialize_with = "optional_text",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub up: Option<String>,
+    #[serde(
+        default,
+        deserialize_with = "optional_text",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub down: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub obfs: Option<String>,
+    #[serde(
+        default,
+        deserialize_with = "optional_text",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub obfs_password: Option<String>,
+    #[serde(
+        default,
+        deserialize_with = "optional_bool",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub udp: Option<bool>,
+    #[serde(flatten)]
+    pub tls: Tls,
+    #[serde(flatten)]
+    pub extra: Mapping,
+}
+
+impl Hysteria2 {
+    pub fn up_mbps(&self) -> Option<u64> {
+        self.up.as_deref().and_then(mbps)
+    }
+
+    pub fn down_mbps(&self) -> Option<u64> {
+        self.down.as_deref().and_then(mbps)
+    }
+}
+
+/// Bandwidth in Mbps from values like `100` or `"100 Mbps"`
+fn mbps(value: &str) -> Option<u64> {
+    let digits: String = value.chars().take_while(char::is_ascii_digit).collect();
+    digits.parse().ok()
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Tuic {
+    #[serde(flatten)]
+    pub endpoint: Endpoint,
+    /// TUIC v5 credentials
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uuid: Option<String>,
+    #[serde(
+        default,
+        deserialize_with = "optional_text",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub password: Option<String>,
+    /// TUIC v4 credential
+    #[serde(
+        default,
+        deserialize_with = "optional_text",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub token: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub congestion_controller: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub udp_relay_mode: Option<String>,
+    #[serde(
+        default,
+        deserialize_with = "optional_bool",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub udp: Option<bool>,
+    #[serde(flatten)]
+    pub tls: Tls,
+    #[serde(flatten)]
+    pub extra: Mapping,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct WireGuard {
+    #[serde(flatten)]
+    pub endpoint: Endpoint,
+    pub private_key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pre_shared_key: Option<String>,
+    /// Interface addresses
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ip: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ipv6: Option<String>,
+    #[serde(
+        default,
+        deserialize_with = "optional_bool",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub udp: Option<bool>,
+    #[serde(flatten)]
+    pub extra: Mapping,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Http {
+    #[serde(flatten)]
+    pub endpoint: Endpoint,
+    #[serde(
+        default,
+        deserialize_with = "optional_text",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub username: Option<String>,
+    #[serde(
+        default,
+        deserialize_with = "optional_text",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub password: Option<String>,
+    #[serde(flatten)]
+    pub tls: Tls,
+    #[serde(flatten)]
+    pub extra: Mapping,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Socks5 {
+    #[serde(flatten)]
+    pub endpoint: Endpoint,
+    #[serde(
+        default,
+        deserialize_with = "optional_text",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub username: Option<String>,
+    #[serde(
+        default,
+        deserialize_with = "optional_text",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub password: Option<String>,
+    #[serde(
+        default,
+        deserialize_with = "optional_bool",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub udp: Option<bool>,
+    #[serde(flatten)]
+    pub tls: Tls,
+    #[serde(flatten)]
+    pub extra: Mapping,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Snell {
+    #[serde(flatten)]
+    pub endpoint: Endpoint,
+    #[serde(deserialize_with = "text")]
+    pub psk: String,
+    #[serde(
+        default,
+        deserialize_with = "optional_number",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub version: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub obfs_opts: Option<PluginOpts>,
+    #[serde(
+        default,
+        deserialize_with = "optional_bool",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub udp: Option<bool>,
+    #[serde(flatten)]
+    pub extra: Mapping,
+}
+
+/// A scalar written either as a string or as a number, e.g. `password: 123456` or `port: "443"`
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Scalar {
+    Text(String),
+    Unsigned(u64),
+    Signed(i64),
+    Float(f64),
+}
+
+impl Scalar {
+    fn into_text(self) -> String {
+        match self {
+            Scalar::Text(s) => s,
+            Scalar::Unsigned(n) => n.to_string(),
+            Scalar::Signed(n) => n.to_string(),
+            Scalar::Float(n) => n.to_string(),
+        }
+    }
+}
+
+fn text<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    Scalar::deserialize(deserializer).map(Scalar::into_text)
+}
+
+fn optional_text<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+    Option::<Scalar>::deserialize(deserializer).map(|scalar| scalar.map(Scalar::into_text))
+}
+
+/// A proxy name, empty when it is null
+fn name<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    optional_text(deserializer).map(Option::unwrap_or_default)
+}
+
+/// A flag written as a boolean, a string or a number, e.g. `udp: "true"`, read the way Clash reads it
+fn optional_bool<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<bool>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Flag {
+        Bool(bool),
+        Scalar(Scalar),
+    }
+
+    let scalar = match Option::<Flag>::deserialize(deserializer)? {
+        None => return Ok(None),
+        Some(Flag::Bool(flag)) => return Ok(Some(flag)),
+        Some(Flag::Scalar(scalar)) => scalar,
+    };
+    match scalar {
+        Scalar::Text(text) => match text.trim() {
+            "1" | "t" | "T" | "true" | "TRUE" | "True" => Ok(Some(true)),
+            "" | "0" | "f" | "F" | "false" | "FALSE" | "False" => Ok(Some(false)),
+            _ => Err(serde::de::Error::custom(format!(
+                "invalid boolean '{}'",
+                text
+            ))),
+        },
+        Scalar::Unsigned(n) => Ok(Some(n != 0)),
+        Scalar::Signed(n) => Ok(Some(n != 0)),
+        Scalar::Float(n) => Ok(Some(n != 0.0)),
+    }
+}
+
+fn number<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: std::str::FromStr,
+{
+    let text = text(deserializer)?;
+    text.trim()
+        .parse()
+        .map_err(|_| serde::de::Error::custom(format!("invalid number '{}'", text)))
+}
+
+fn optional_number<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: std::str::FromStr,
+{
+    match optional_text(deserializer)? {
+        Some(text) => text
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(|_| serde::de::Error::custom(format!("invalid number '{}'", text))),
+        None => Ok(None),
+    }
+}
@@ -0,0 +1,269 @@
+use crate::backend::{
+    Backend, Rendered, Rule, check_line, convert_each, dropped_comments, is_country_code,
+    resolve_groups, rule_list_url, section, unsupported,
+};
+use crate::converter::{ConvertError, ConvertOptions, OutputConfig, ProxyGroup};
+use crate::proxy::{Proxy, Tls, Transport};
+
+/// Quantumult X profile with `[server_local]`, `[policy]`, `[filter_remote]` and `[filter_local]`
+pub struct QuantumultX;
+
+impl Backend for QuantumultX {
+    fn render(
+        &self,
+        output: &OutputConfig,
+        options: &ConvertOptions,
+    ) -> Result<Rendered, ConvertError> {
+        let (proxies, dropped) = convert_each(&output.proxies, server_line);
+        let proxy_names: Vec<String> = proxies.iter().map(|(name, _)| name.clone()).collect();
+        let groups = resolve_groups(&output.proxy_groups, &proxy_names)?;
+
+        let server_lines: Vec<String> = proxies.into_iter().map(|(_, line)| line).collect();
+        let policy_lines: Vec<String> = groups
+            .iter()
+            .map(|(group, members)| policy_line(group, members))
+            .collect();
+
+        // GEOSITE categories become remote filters, the rest stays local
+        let mut remote_filters = Vec::new();
+        let mut local_filters = Vec::new();
+        for rule in &output.rules {
+            match Rule::parse(rule) {
+                Some(Rule::GeoSite { name, target }) => {
+                    if let Some(url) = rule_list_url("QuantumultX", name) {
+                        remote_filters.push(format!(
+                            "{}, tag={}, force-policy={}, update-interval=86400, opt-parser=false, enabled=true",
+                            url,
+                            name,
+                            policy(target)
+                        ));
+                    }
+                }
+                Some(Rule::GeoIp { code, target }) if is_country_code(code) => {
+                    local_filters.push(format!(
+                        "geoip, {}, {}",
+                        code.to_lowercase(),
+                        policy(target)
+                    ));
+                }
+                Some(Rule::Match { target }) => {
+                    local_filters.push(format!("final, {}", policy(target)));
+                }
+                _ => {}
+            }
+        }
+
+        // The health-check URL is global in Quantumult X
+        let general = vec![format!("server_check_url={}", options.balance.url)];
+
+        let content = [
+            dropped_comments(&dropped, ";"),
+            section("general", &general),
+            section("server_local", &server_lines),
+            section("policy", &policy_lines),
+            section("filter_remote", &remote_filters),
+            section("filter_local", &local_filters),
+        ]
+        .into_iter()
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n");
+        Ok(Rendered { content, dropped })
+    }
+}
+
+/// Built-in policies are lowercase in Quantumult X
+fn policy(name: &str) -> String {
+    match name {
+        "DIRECT" => "direct".to_string(),
+        "REJECT" | "REJECT-DROP" => "reject".to_string(),
+        _ => name.to_string(),
+    }
+}
+
+/// A `[server_local]` line, or the reason the proxy is not supported
+fn server_line(proxy: &Proxy) -> Result<String, String> {
+    let mut fields: Vec<String> = Vec::new();
+
+    let (qx_type, endpoint) = match proxy {
+        Proxy::Shadowsocks(ss) => {
+            fields.push(format!("method={}", ss.cipher));
+            fields.push(format!("password={}", ss.password));
+            let opts = ss.plugin_opts.clone().unwrap_or_default();
+            match ss.plugin.as_deref() {
+                None => {}
+                Some("obfs") => {
+                    fields.push(format!("obfs={}", opts.mode.as_deref().unwrap_or("http")));
+                    if let Some(host) = &opts.host {
+                        fields.push(format!("obfs-host={}", host));
+                    }
+                }
+                Some("v2ray-plugin") => {
+                    let obfs = if opts.tls == Some(true) { "wss" } else { "ws" };
+                    fields.push(format!("obfs={}", obfs));
+                    if let Some(host) = &opts.host {
+                        fields.push(format!("obfs-host={}", host));
+                    }
+                    if let Some(path) = &opts.path {
+                        fields.push(format!("obfs-uri={}", path));
+                    }
+                }
+                Some(plugin) => return Err(format!("unsupported plugin '{}'", plugin)),
+            }
+            if ss.udp == Some(true) {
+                fields.push("udp-relay=true".to_string());
+            }
+            ("shadowsocks", &ss.endpoint)
+        }
+        Proxy::Vmess(vmess) => {
+            let method = match vmess.cipher.as_deref() {
+                Some("aes-128-gcm") => "aes-128-gcm",
+                Some("none") => "none",
+                _ => "chacha20-poly1305",
+            };
+            fields.push(format!("method={}", method));
+            fields.push(format!("password={}", vmess.uuid));
+            push_obfs(
+                &vmess.transport,
+                &vmess.tls,
+                vmess.tls.enabled(),
+                &mut fields,
+            )?;
+            if vmess.aead() {
+                fields.push("aead=true".to_string());
+            }
+            ("vmess", &vmess.endpoint)
+        }
+        Proxy::Vless(vless) => {
+            if vless.reality_opts.is_some() || vless.flow.is_some() {
+                return Err("REALITY and flow are not supported".to_string());
+            }
+            fields.push("method=none".to_string());
+            fields.push(format!("password={}", vless.uuid));
+            push_obfs(
+                &vless.transport,
+                &vless.tls,
+                vless.tls.enabled(),
+                &mut fields,
+            )?;
+            ("vless", &vless.endpoint)
+        }
+        Proxy::Trojan(trojan) => {
+            fields.push(format!("password={}", trojan.password));
+            // Trojan over TCP takes `over-tls`, the `obfs=over-tls` form is for vmess
+            if trojan.transport.network() == "tcp" {
+                fields.push("over-tls=true".to_string());
+                push_tls(&trojan.tls, &mut fields);
+            } else {
+                push_obfs(&trojan.transport, &trojan.tls, true, &mut fields)?;
+            }
+            ("trojan", &trojan.endpoint)
+        }
+        Proxy::Http(http) => {
+            push_credentials(&http.username, &http.password, &mut fields);
+            if http.tls.enabled() {
+                fields.push("over-tls=true".to_string());
+                push_tls(&http.tls, &mut fields);
+            }
+            ("http", &http.endpoint)
+        }
+        Proxy::Socks5(socks) => {
+            push_credentials(&socks.username, &socks.password, &mut fields);
+            if socks.tls.enabled() {
+                fields.push("over-tls=true".to_string());
+                push_tls(&socks.tls, &mut fields);
+            }
+            ("socks5", &socks.endpoint)
+        }
+        _ => return Err(unsupported(proxy)),
+    };
+
+    check_line(&endpoint.name, &fields)?;
+    fields.push(format!("tag={}", endpoint.name));
+    Ok(format!(
+        "{}={}:{}, {}",
+        qx_type,
+        endpoint.server,
+        endpoint.port,
+        fields.join(", ")
+    ))
+}
+
+fn push_credentials(
+    username: &Option<String>,
+    password: &Option<String>,
+    fields: &mut Vec<String>,
+) {
+    if let Some(username) = username {
+        fields.push(format!("username={}", username));
+        fields.push(format!(
+            "password={}",
+            password.as_deref().unwrap_or_default()
+        ));
+    }
+}
+
+/// TLS options
+fn push_tls(tls: &Tls, fields: &mut Vec<String>) {
+    if let Some(sni) = tls.server_name() {
+        fields.push(format!("tls-host={}", sni));
+    }
+    if tls.insecure() {
+        fields.push("tls-verification=false".to_string());
+    }
+}
+
+/// Transport and TLS as `obfs` (`over-tls`, `ws` or `wss`)
+fn push_obfs(
+    transport: &Transport,
+    tls: &Tls,
+    tls_enabled: bool,
+    fields: &mut Vec<String>,
+) -> Result<(), String> {
+    match transport.network() {
+        "tcp" => {
+            if tls_enabled {
+                fields.push("obfs=over-tls".to_string());
+                push_tls(tls, fields);
+            }
+        }
+        "ws" => {
+            let ws = transport.ws_opts.clone().unwrap_or_default();
+            let obfs = if tls_enabled { "wss" } else { "ws" };
+            fields.push(format!("obfs={}", obfs));
+            if let Some(host) = ws.host() {
+                fields.push(format!("obfs-host={}", host));
+            }
+            if let Some(path) = &ws.path {
+                fields.push(format!("obfs-uri={}", path));
+            }
+            if tls_enabled {
+                push_tls(tls, fields);
+            }
+        }
+        network => return Err(format!("unsupported transport '{}'", network)),
+    }
+    Ok(())
+}
+
+/// A `[policy]` line
+fn policy_line(group: &ProxyGroup, members: &[String]) -> String {
+    let policy_type = match group.group_type.as_str() {
+        "select" => "static",
+        "url-test" => "url-latency-benchmark",
+        "fallback" => "available",
+        // Quantumult X only balances by round-robin
+        _ => "round-robin",
+    };
+    let mut fields = vec![group.name.clone()];
+    fields.extend(members.iter().map(|member| policy(member)));
+    if matches!(policy_type, "url-latency-benchmark" | "available") {
+        if let Some(interval) = group.interval {
+            fields.push(format!("check-interval={}", interval));
+        }
+        if let Some(tolerance) = group.tolerance {
+            fields.push(format!("tolerance={}", tolerance));
+        }
+    }
+    format!("{}={}", policy_type, fields.join(", "))
+}
@@ -0,0 +1,277 @@
+use crate::converter::ConvertError;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// Token boundary for ASCII keywords; digits count as separators so `HK01` matches `hk`
+const TOKEN_START: &str = "(?:^|[^A-Za-z])";
+const TOKEN_END: &str = "(?:[^A-Za-z]|$)";
+
+/// A region load-balance group and the proxy names it matches
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RegionRule {
+    /// Group name, e.g. `香港负载组`
+    pub name: String,
+    /// Names and codes of the region, e.g. `香港`, `港`, `hk`, `hong kong`.
+    /// ASCII keywords only match as standalone tokens, others anywhere in the name.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keywords: Vec<String>,
+    /// Additional raw regex matched against proxy names
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    /// Flag emoji of the region, also matched in proxy names
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub emoji: Option<String>,
+    /// Position in the group list; rules without one come last
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub order: Option<i32>,
+}
+
+impl RegionRule {
+    fn new(name: &str, keywords: &[&str], emoji: &str, order: i32) -> Self {
+        RegionRule {
+            name: name.to_string(),
+            keywords: keywords.iter().map(|k| k.to_string()).collect(),
+            pattern: None,
+            emoji: Some(emoji.to_string()),
+            order: Some(order),
+        }
+    }
+
+    /// Keywords matched anywhere in a name, longest first
+    fn substring_keywords(&self) -> Vec<&str> {
+        let mut keywords: Vec<&str> = self
+            .keywords
+            .iter()
+            .map(String::as_str)
+            .filter(|k| !is_token_keyword(k))
+            .collect();
+        keywords.sort_by_key(|k| std::cmp::Reverse(k.chars().count()));
+        keywords
+    }
+
+    /// Regex for the group `filter`
+    pub fn filter(&self) -> String {
+        let mut alternatives: Vec<String> = self
+            .substring_keywords()
+            .into_iter()
+            .map(regex::escape)
+            .collect();
+
+        if let Some(emoji) = self.emoji.as_deref().filter(|e| !e.is_empty()) {
+            alternatives.push(regex::escape(emoji));
+        }
+
+        let mut tokens: Vec<&str> = self
+            .keywords
+            .iter()
+            .map(String::as_str)
+            .filter(|k| is_token_keyword(k))
+            .collect();
+        tokens.sort_by_key(|k| std::cmp::Reverse(k.len()));
+        if !tokens.is_empty() {
+            let tokens: Vec<String> = tokens
+                .into_iter()
+                .map(|k| regex::escape(&k.to_lowercase()).replace(' ', "[ _-]?"))
+                .collect();
+            alternatives.push(format!(
+                "{}(?:{}){}",
+                TOKEN_START,
+                tokens.join("|"),
+                TOKEN_END
+            ));
+        }
+
+        if let Some(pattern) = self.pattern.as_deref().filter(|p| !p.is_empty()) {
+            alternatives.push(format!("(?:{})", pattern));
+        }
+
+        if alternatives.is_empty() {
+            // A rule without keywords matches nothing
+            return "$^".to_string();
+        }
+        format!("(?i){}", alternatives.join("|"))
+    }
+
+    /// Regex for names that contain one of this rule's keywords only as part of a longer
+    /// keyword of another rule, e.g. `新加坡` for the `加` of `加拿大负载组`
+    pub fn exclude_filter(&self, regions: &[RegionRule]) -> Option<String> {
+        let own = self.substring_keywords();
+        let mut longer: Vec<&str> = regions
+            .iter()
+            .filter(|other| other.name != self.name)
+            .flat_map(|other| other.substring_keywords())
+            .filter(|candidate| {
+                own.iter().any(|k| candidate.contains(k) && candidate != k)
+                    && !own.contains(candidate)
+            })
+            .collect();
+        longer.sort_by_key(|k| std::cmp::Reverse(k.chars().count()));
+        longer.dedup();
+
+        if longer.is_empty() {
+            return None;
+        }
+        let escaped: Vec<String> = longer.into_iter().map(regex::escape).collect();
+        Some(escaped.join("|"))
+    }
+}
+
+/// ASCII keywords are matched as tokens, everything else as a substring
+fn is_token_keyword(keyword: &str) -> bool {
+    keyword
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == ' ')
+}
+
+/// A region rule compiled for matching proxy names
+#[derive(Debug, Clone)]
+pub struct RegionMatcher {
+    pub name: String,
+    /// Regex source for the group `filter`
+    pub filter: String,
+    /// Regex source for names excluded despite matching `filter`
+    pub exclude_filter: Option<String>,
+    /// Flag emoji of the region
+    pub emoji: Option<String>,
+    include: Regex,
+    exclude: Option<Regex>,
+}
+
+impl RegionMatcher {
+    /// Compile a rule; `regions` is the full table used to resolve keyword overlaps
+    pub fn new(rule: &RegionRule, regions: &[RegionRule]) -> Result<Self, ConvertError> {
+        let filter = rule.filter();
+        let exclude_filter = rule.exclude_filter(regions);
+        let compile = |pattern: &str| {
+            Regex::new(pattern).map_err(|e| {
+                ConvertError(format!("Invalid pattern for region {}: {}", rule.name, e))
+            })
+        };
+
+        Ok(RegionMatcher {
+            name: rule.name.clone(),
+            include: compile(&filter)?,
+            exclude: exclude_filter.as_deref().map(compile).transpose()?,
+            filter,
+            exclude_filter,
+            emoji: rule.emoji.clone().filter(|e| !e.is_empty()),
+        })
+    }
+
+    /// Check if a proxy name belongs to this region
+    pub fn is_match(&self, name: &str) -> bool {
+        self.matches_filter(name) && !self.exclude.as_ref().is_some_and(|re| re.is_match(name))
+    }
+
+    /// Check if a proxy name matches `filter`, which keeps it out of 其他负载组
+    pub fn matches_filter(&self, name: &str) -> bool {
+        self.include.is_match(name)
+    }
+}
+
+/// Compile every rule of a region table
+pub fn region_matchers(regions: &[RegionRule]) -> Result<Vec<RegionMatcher>, ConvertError> {
+    regions
+        .iter()
+        .map(|rule| RegionMatcher::new(rule, regions))
+        .collect()
+}
+
+/// A region table document, either a bare list of rules or a mapping with options
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum RegionConfig {
+    Rules(Vec<RegionRule>),
+    Table {
+        /// Replace the default table instead of merging into it
+        #[serde(default)]
+        replace: bool,
+        regions: Vec<RegionRule>,
+    },
+}
+
+/// The built-in region table
+pub fn default_regions() -> Vec<RegionRule> {
+    vec![
+        RegionRule::new("香港负载组", &["香港", "港", "hk", "hong kong"], "🇭🇰", 10),
+        RegionRule::new("台湾负载组", &["台湾", "台", "tw", "taiwan"], "🇹🇼", 20),
+        RegionRule::new("日本负载组", &["日本", "日", "jp", "japan"], "🇯🇵", 30),
+        RegionRule::new(
+            "新加坡负载组",
+            &["新加坡", "新", "sg", "singapore"],
+            "🇸🇬",
+            40,
+        ),
+        RegionRule::new(
+            "美国负载组",
+            &["美国", "美", "us", "usa", "united states", "america"],
+            "🇺🇸",
+            50,
+        ),
+        RegionRule::new("韩国负载组", &["韩国", "韩", "kr", "korea"], "🇰🇷", 60),
+        RegionRule::new(
+            "英国负载组",
+            &["英国", "英", "uk", "britain", "united kingdom"],
+            "🇬🇧",
+            70,
+        ),
+        RegionRule::new("德国负载组", &["德国", "德", "de", "germany"], "🇩🇪", 80),
+        RegionRule::new("法国负载组", &["法国", "法", "fr", "france"], "🇫🇷", 90),
+        RegionRule::new("加拿大负载组", &["加拿大", "加", "ca", "canada"], "🇨🇦", 100),
+        RegionRule::new(
+            "澳大利亚负载组",
+            &["澳大利亚", "澳", "au", "australia"],
+            "🇦🇺",
+            110,
+        ),
+        RegionRule::new(
+            "马来西亚负载组",
+            &["马来西亚", "马来", "my", "malaysia"],
+            "🇲🇾",
+            120,
+        ),
+        RegionRule::new("土耳其负载组", &["土耳其", "tr", "turkey"], "🇹🇷", 130),
+        RegionRule::new("阿根廷负载组", &["阿根廷", "ar", "argentina"], "🇦🇷", 140),
+    ]
+}
+
+/// Parse a region table from JSON or YAML.
+/// A bare list or `{regions: [...]}` is merged into the default table by name;
+/// `{replace: true, regions: [...]}` replaces it.
+pub fn parse_region_rules(text: &str) -> Result<Vec<RegionRule>, ConvertError> {
+    let config: RegionConfig = serde_yaml::from_str(text)
+        .map_err(|e| ConvertError(format!("Invalid region table: {}", e)))?;
+
+    let (replace, rules) = match config {
+        RegionConfig::Rules(rules) => (false, rules),
+        RegionConfig::Table { replace, regions } => (replace, regions),
+    };
+
+    for rule in &rules {
+        if rule.name.trim().is_empty() {
+            return Err(ConvertError("Region name must not be empty".to_string()));
+        }
+        if let Some(pattern) = &rule.pattern {
+            Regex::new(pattern).map_err(|e| {
+                ConvertError(format!("Invalid pattern for region {}: {}", rule.name, e))
+            })?;
+        }
+    }
+
+    let mut regions = if replace {
+        Vec::new()
+    } else {
+        default_regions()
+    };
+    for rule in rules {
+        match regions.iter_mut().find(|r| r.name == rule.name) {
+            Some(existing) => *existing = rule,
+            None => regions.push(rule),
+        }
+    }
+
+    // Stable sort keeps the given order for rules without an explicit position
+    regions.sort_by_key(|rule| rule.order.unwrap_or(i32::MAX));
+
+    Ok(regions)
+}
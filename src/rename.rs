@@ -0,0 +1,132 @@
+use crate::converter::{ConvertError, ConvertOptions, is_info_node};
+use crate::proxy::Proxy;
+use crate::region::region_matchers;
+use regex::Regex;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::LazyLock;
+
+/// Flag emoji, each a pair of regional indicator symbols
+static FLAG_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"[\u{1F1E6}-\u{1F1FF}]{2}\s*").expect("flag pattern is valid"));
+
+/// A node number: trailing digits after a separator, or the whole name
+static TRAILING_NUMBER_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?:^|[\s_#-]+)\d+$").expect("trailing number pattern is valid"));
+
+/// A regex replacement applied to proxy names, written `pattern@replacement`.
+/// Without `@` the matches are removed; the replacement may refer to groups as `$1`.
+#[derive(Debug, Clone)]
+pub struct RenameRule {
+    pattern: Regex,
+    replacement: String,
+}
+
+impl FromStr for RenameRule {
+    type Err = ConvertError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // The pattern may contain `@`, the replacement may not
+        let (pattern, replacement) = s.rsplit_once('@').unwrap_or((s, ""));
+        // An empty pattern matches between every character
+        if pattern.is_empty() {
+            return Err(ConvertError(format!(
+                "Invalid rename '{}': empty pattern; end the rule with '@' to remove a pattern that contains '@'",
+                s
+            )));
+        }
+        let pattern = Regex::new(pattern)
+            .map_err(|e| ConvertError(format!("Invalid rename '{}': {}", s, e)))?;
+        Ok(RenameRule {
+            pattern,
+            replacement: replacement.to_string(),
+        })
+    }
+}
+
+impl RenameRule {
+    /// Replace every match in a name
+    pub fn apply(&self, name: &str) -> String {
+        self.pattern
+            .replace_all(name, self.replacement.as_str())
+            .trim()
+            .to_string()
+    }
+}
+
+/// Rename proxies before grouping: apply the `rename` rules in order, replace any flags with
+/// the one of the detected region with `emoji`, then renumber with `number`.
+/// Info nodes only go through the rules.
+pub fn rename_proxies(proxies: &mut [Proxy], options: &ConvertOptions) -> Result<(), ConvertError> {
+    let mut names: Vec<String> = proxies
+        .iter()
+        .map(|proxy| proxy.name().unwrap_or_default())
+        .collect();
+
+    for rule in &options.rename {
+        for name in names.iter_mut() {
+            *name = rule.apply(name);
+        }
+    }
+
+    if options.emoji {
+        let matchers = region_matchers(&options.regions)?;
+        for name in names.iter_mut().filter(|name| !is_info_node(name)) {
+            let emoji = matchers
+                .iter()
+                .find(|matcher| matcher.is_match(name))
+                .and_then(|matcher| matcher.emoji.as_deref());
+            if let Some(emoji) = emoji {
+                // Another flag would show the wrong icon and match that region's group too
+                let bare = FLAG_REGEX.replace_all(name, "");
+                *name = format!("{} {}", emoji, bare.trim()).trim_end().to_string();
+            }
+        }
+    }
+
+    if options.number {
+        renumber(&mut names);
+    }
+
+    for (proxy, name) in proxies.iter_mut().zip(names) {
+        if proxy.name().unwrap_or_default() != name {
+            proxy.set_name(name);
+        }
+    }
+    Ok(())
+}
+
+/// Number the proxies that share a name apart from its trailing number in order,
+/// e.g. `香港 1`, `香港` and `香港-3` become `香港 01`, `香港 02` and `香港 03`.
+/// Digits without a separator are part of the name, as in `IPv6`.
+/// Names that no other proxy shares are left as they are.
+fn renumber(names: &mut [String]) {
+    let bases: Vec<Option<String>> = names
+        .iter()
+        .map(|name| {
+            (!name.is_empty() && !is_info_node(name))
+                .then(|| TRAILING_NUMBER_REGEX.replace(name, "").to_string())
+        })
+        .collect();
+
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for base in bases.iter().flatten() {
+        *counts.entry(base).or_default() += 1;
+    }
+
+    let mut next: HashMap<&str, usize> = HashMap::new();
+    for (name, base) in names.iter_mut().zip(&bases) {
+        let Some(base) = base else { continue };
+        // A name of its own keeps its number, or lack of one
+        if counts[base.as_str()] == 1 {
+            continue;
+        }
+        let number = next.entry(base).or_default();
+        *number += 1;
+        let width = counts[base.as_str()].to_string().len().max(2);
+        *name = match base.is_empty() {
+            true => format!("{:0width$}", number),
+            false => format!("{} {:0width$}", base, number),
+        };
+    }
+}
@@ -0,0 +1,263 @@
+use crate::converter::ConvertError;
+use serde::Serialize;
+
+/// Refresh interval of the remote rule sets in seconds
+const RULE_SET_INTERVAL: u32 = 86400;
+
+const LOYALSOLDIER_BASE: &str = "https://cdn.jsdelivr.net/gh/Loyalsoldier/clash-rules@release";
+const ACL4SSR_BASE: &str = "https://cdn.jsdelivr.net/gh/ACL4SSR/ACL4SSR@master/Clash/Providers";
+
+/// Routing targets used by the templates
+const DIRECT: &str = "直接连接";
+const PROXY: &str = "默认流量";
+const REJECT: &str = "REJECT";
+
+/// Policies built into Clash that can be used as a rule target without a group
+pub const BUILTIN_POLICIES: [&str; 4] = ["DIRECT", "REJECT", "REJECT-DROP", "PASS"];
+
+/// A `rule-providers` entry
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RuleProvider {
+    #[serde(rename = "type")]
+    pub provider_type: String,
+    pub behavior: String,
+    pub url: String,
+    pub path: String,
+    pub interval: u32,
+}
+
+/// A remote rule set and the group its matches are routed to
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleSet {
+    pub name: String,
+    /// `domain`, `ipcidr` or `classical`
+    pub behavior: String,
+    pub url: String,
+    /// Proxy group or built-in policy for `RULE-SET,<name>,<target>`
+    pub target: String,
+}
+
+impl RuleSet {
+    fn new(name: &str, behavior: &str, url: String, target: &str) -> Self {
+        RuleSet {
+            name: name.to_string(),
+            behavior: behavior.to_string(),
+            url,
+            target: target.to_string(),
+        }
+    }
+
+    /// The provider that downloads this set
+    pub fn provider(&self) -> RuleProvider {
+        RuleProvider {
+            provider_type: "http".to_string(),
+            behavior: self.behavior.clone(),
+            url: self.url.clone(),
+            path: format!("./ruleset/{}.yaml", self.name),
+            interval: RULE_SET_INTERVAL,
+        }
+    }
+}
+
+/// Preset list of rule sets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleTemplate {
+    /// Loyalsoldier/clash-rules, domain and ipcidr sets
+    Loyalsoldier,
+    /// ACL4SSR Clash providers, classical sets
+    Acl4ssr,
+}
+
+impl RuleTemplate {
+    /// The rule sets of this template, in rule order
+    pub fn rule_sets(&self) -> Vec<RuleSet> {
+        match self {
+            RuleTemplate::Loyalsoldier => {
+                let set = |name: &str, behavior: &str, target: &str| {
+                    RuleSet::new(
+                        name,
+                        behavior,
+                        format!("{}/{}.txt", LOYALSOLDIER_BASE, name),
+                        target,
+                    )
+                };
+                vec![
+                    set("applications", "classical", DIRECT),
+                    set("private", "domain", DIRECT),
+                    set("reject", "domain", REJECT),
+                    set("icloud", "domain", DIRECT),
+                    set("apple", "domain", DIRECT),
+                    set("google", "domain", PROXY),
+                    set("proxy", "domain", PROXY),
+                    set("direct", "domain", DIRECT),
+                    set("lancidr", "ipcidr", DIRECT),
+                    set("cncidr", "ipcidr", DIRECT),
+                    set("telegramcidr", "ipcidr", PROXY),
+                ]
+            }
+            RuleTemplate::Acl4ssr => {
+                let set = |name: &str, path: &str, target: &str| {
+                    RuleSet::new(
+                        name,
+                        "classical",
+                        format!("{}/{}.yaml", ACL4SSR_BASE, path),
+                        target,
+                    )
+                };
+                vec![
+                    set("LocalAreaNetwork", "LocalAreaNetwork", DIRECT),
+                    set("UnBan", "UnBan", DIRECT),
+                    set("BanAD", "BanAD", REJECT),
+                    set("BanProgramAD", "BanProgramAD", REJECT),
+                    set("GoogleCN", "GoogleCN", DIRECT),
+                    set("SteamCN", "Ruleset/SteamCN", DIRECT),
+                    set("Microsoft", "Microsoft", DIRECT),
+                    set("Apple", "Apple", DIRECT),
+                    set("ProxyMedia", "ProxyMedia", PROXY),
+                    set("Telegram", "Ruleset/Telegram", PROXY),
+                    set("ProxyLite", "ProxyLite", PROXY),
+                    set("ChinaDomain", "ChinaDomain", DIRECT),
+                    set("ChinaCompanyIp", "ChinaCompanyIp", DIRECT),
+                ]
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for RuleTemplate {
+    type Err = ConvertError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "loyalsoldier" => Ok(RuleTemplate::Loyalsoldier),
+            "acl4ssr" => Ok(RuleTemplate::Acl4ssr),
+            _ => Err(ConvertError(format!(
+                "Invalid rules '{}', expected loyalsoldier or acl4ssr",
+                s
+            ))),
+        }
+    }
+}
+
+/// Apply `set:target` overrides, e.g. `google:美国负载组,reject:DIRECT`
+pub fn apply_routes(rule_sets: &mut [RuleSet], routes: &str) -> Result<(), ConvertError> {
+    for route in routes.split(',').map(str::trim).filter(|r| !r.is_empty()) {
+        let Some((name, target)) = route.split_once(':') else {
+            return Err(ConvertError(format!(
+                "Invalid route '{}', expected <rule set>:<group>",
+                route
+            )));
+        };
+        let (name, target) = (name.trim(), target.trim());
+        if target.is_empty() {
+            return Err(ConvertError(format!("Missing group in route '{}'", route)));
+        }
+        let Some(rule_set) = rule_sets.iter_mut().find(|set| set.name == name) else {
+            let names: Vec<&str> = rule_sets.iter().map(|set| set.name.as_str()).collect();
+            return Err(ConvertError(format!(
+                "Unknown rule set '{}' in route, expected one of: {}",
+                name,
+                names.join(", ")
+            )));
+        };
+        rule_set.target = target.to_string();
+    }
+    Ok(())
+}
+
+/// An app with its own select group and routing rules
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Service {
+    Netflix,
+    OpenAi,
+    Telegram,
+    YouTube,
+    Steam,
+}
+
+impl Service {
+    /// Name of the select group
+    pub fn group_name(&self) -> &'static str {
+        match self {
+            Service::Netflix => "Netflix",
+            Service::OpenAi => "OpenAI",
+            Service::Telegram => "Telegram",
+            Service::YouTube => "YouTube",
+            Service::Steam => "Steam",
+        }
+    }
+
+    /// Rules sending the service's traffic to its group
+    pub fn rules(&self) -> Vec<String> {
+        let (geosites, geoips): (&[&str], &[&str]) = match self {
+            Service::Netflix => (&["netflix"], &["netflix"]),
+            Service::OpenAi => (&["openai"], &[]),
+            Service::Telegram => (&["telegram"], &["telegram"]),
+            Service::YouTube => (&["youtube"], &[]),
+            Service::Steam => (&["steam"], &[]),
+        };
+        let group = self.group_name();
+
+        let domains = geosites
+            .iter()
+            .map(|site| format!("GEOSITE,{},{}", site, group));
+        let ips = geoips
+            .iter()
+            .map(|ip| format!("GEOIP,{},{},no-resolve", ip, group));
+        domains.chain(ips).collect()
+    }
+}
+
+impl std::str::FromStr for Service {
+    type Err = ConvertError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "netflix" => Ok(Service::Netflix),
+            "openai" => Ok(Service::OpenAi),
+            "telegram" => Ok(Service::Telegram),
+            "youtube" => Ok(Service::YouTube),
+            "steam" => Ok(Service::Steam),
+            _ => Err(ConvertError(format!(
+                "Invalid service '{}', expected netflix, openai, telegram, youtube or steam",
+                s
+            ))),
+        }
+    }
+}
+
+/// A service group and the group it selects by default
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServiceRoute {
+    pub service: Service,
+    /// Group selected first instead of 默认流量
+    pub group: Option<String>,
+}
+
+/// Parse `service[:group]` entries, e.g. `openai:美国负载组,netflix`
+pub fn parse_services(text: &str) -> Result<Vec<ServiceRoute>, ConvertError> {
+    let mut routes: Vec<ServiceRoute> = Vec::new();
+    for entry in text.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let (service, group) = match entry.split_once(':') {
+            Some((service, group)) if !group.trim().is_empty() => {
+                (service.trim(), Some(group.trim().to_string()))
+            }
+            Some(_) => {
+                return Err(ConvertError(format!(
+                    "Missing group in service '{}'",
+                    entry
+                )));
+            }
+            None => (entry, None),
+        };
+        let service: Service = service.parse()?;
+        if routes.iter().any(|route| route.service == service) {
+            return Err(ConvertError(format!(
+                "Service '{}' is listed more than once",
+                service.group_name()
+            )));
+        }
+        routes.push(ServiceRoute { service, group });
+    }
+    Ok(routes)
+}
@@ -0,0 +1,208 @@
+use crate::converter::ConvertError;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// General Clash settings placed at the top of the output
+#[derive(Debug, Clone, PartialEq, Serialize, Default)]
+pub struct GeneralSettings {
+    #[serde(rename = "mixed-port", skip_serializing_if = "Option::is_none")]
+    pub mixed_port: Option<u16>,
+    #[serde(rename = "allow-lan", skip_serializing_if = "Option::is_none")]
+    pub allow_lan: Option<bool>,
+    #[serde(rename = "bind-address", skip_serializing_if = "Option::is_none")]
+    pub bind_address: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mode: Option<String>,
+    #[serde(rename = "log-level", skip_serializing_if = "Option::is_none")]
+    pub log_level: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ipv6: Option<bool>,
+    #[serde(
+        rename = "external-controller",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub external_controller: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    #[serde(rename = "unified-delay", skip_serializing_if = "Option::is_none")]
+    pub unified_delay: Option<bool>,
+    #[serde(rename = "tcp-concurrent", skip_serializing_if = "Option::is_none")]
+    pub tcp_concurrent: Option<bool>,
+}
+
+/// Preset for the general settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Profile {
+    /// Local client: listens on localhost only
+    Desktop,
+    /// LAN gateway: accepts connections and controller access from other devices
+    Router,
+}
+
+impl Profile {
+    /// The general settings of this preset
+    pub fn settings(&self) -> GeneralSettings {
+        match self {
+            Profile::Desktop => GeneralSettings {
+                mixed_port: Some(7890),
+                allow_lan: Some(false),
+                bind_address: None,
+                mode: Some("rule".to_string()),
+                log_level: Some("info".to_string()),
+                ipv6: Some(false),
+                external_controller: Some("127.0.0.1:9090".to_string()),
+                secret: None,
+                unified_delay: Some(true),
+                tcp_concurrent: Some(true),
+            },
+            Profile::Router => GeneralSettings {
+                mixed_port: Some(7890),
+                allow_lan: Some(true),
+                bind_address: Some("*".to_string()),
+                mode: Some("rule".to_string()),
+                log_level: Some("warning".to_string()),
+                ipv6: Some(false),
+                external_controller: Some("0.0.0.0:9090".to_string()),
+                secret: None,
+                unified_delay: Some(true),
+                tcp_concurrent: Some(true),
+            },
+        }
+    }
+}
+
+impl std::str::FromStr for Profile {
+    type Err = ConvertError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "desktop" => Ok(Profile::Desktop),
+            "router" => Ok(Profile::Router),
+            _ => Err(ConvertError(format!(
+                "Invalid profile '{}', expected desktop or router",
+                s
+            ))),
+        }
+    }
+}
+
+/// Resolvers for domains in China
+const CN_NAMESERVERS: [&str; 2] = [
+    "https://doh.pub/dns-query",
+    "https://dns.alidns.com/dns-query",
+];
+/// Resolvers for everything else
+const FOREIGN_NAMESERVERS: [&str; 2] = [
+    "https://dns.google/dns-query",
+    "https://cloudflare-dns.com/dns-query",
+];
+
+/// Domains that must resolve to real IPs in fake-ip mode (LAN, connectivity checks, NTP, consoles)
+const FAKE_IP_FILTER: [&str; 10] = [
+    "*.lan",
+    "*.local",
+    "+.msftconnecttest.com",
+    "+.msftncsi.com",
+    "localhost.ptlogin2.qq.com",
+    "+.srv.nintendo.net",
+    "+.stun.playstation.net",
+    "+.xboxlive.com",
+    "time.*.com",
+    "ntp.*.com",
+];
+
+/// The `dns` section
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DnsSettings {
+    pub enable: bool,
+    pub ipv6: bool,
+    #[serde(rename = "enhanced-mode")]
+    pub enhanced_mode: String,
+    #[serde(rename = "fake-ip-range", skip_serializing_if = "Option::is_none")]
+    pub fake_ip_range: Option<String>,
+    #[serde(rename = "fake-ip-filter", skip_serializing_if = "Vec::is_empty")]
+    pub fake_ip_filter: Vec<String>,
+    #[serde(rename = "default-nameserver")]
+    pub default_nameserver: Vec<String>,
+    pub nameserver: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fallback: Vec<String>,
+    #[serde(rename = "fallback-filter", skip_serializing_if = "Option::is_none")]
+    pub fallback_filter: Option<FallbackFilter>,
+    #[serde(
+        rename = "nameserver-policy",
+        skip_serializing_if = "BTreeMap::is_empty"
+    )]
+    pub nameserver_policy: BTreeMap<String, Vec<String>>,
+}
+
+/// Decides when fallback resolvers are used in redir-host mode
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FallbackFilter {
+    pub geoip: bool,
+    #[serde(rename = "geoip-code")]
+    pub geoip_code: String,
+}
+
+/// Preset for the `dns` section
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DnsMode {
+    /// Fake IPs for everything except `FAKE_IP_FILTER`; China domains use China resolvers
+    FakeIp,
+    /// Real IPs from China resolvers, falling back to foreign ones for non-China results
+    RedirHost,
+}
+
+impl DnsMode {
+    /// The DNS settings of this preset
+    pub fn settings(&self) -> DnsSettings {
+        let strings = |items: &[&str]| items.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let nameserver_policy =
+            BTreeMap::from([("geosite:cn,private".to_string(), strings(&CN_NAMESERVERS))]);
+
+        match self {
+            DnsMode::FakeIp => DnsSettings {
+                enable: true,
+                ipv6: false,
+                enhanced_mode: "fake-ip".to_string(),
+                fake_ip_range: Some("198.18.0.1/16".to_string()),
+                fake_ip_filter: strings(&FAKE_IP_FILTER),
+                default_nameserver: strings(&["223.5.5.5", "119.29.29.29"]),
+                nameserver: strings(&FOREIGN_NAMESERVERS),
+                fallback: Vec::new(),
+                fallback_filter: None,
+                nameserver_policy,
+            },
+            DnsMode::RedirHost => DnsSettings {
+                enable: true,
+                ipv6: false,
+                enhanced_mode: "redir-host".to_string(),
+                fake_ip_range: None,
+                fake_ip_filter: Vec::new(),
+                default_nameserver: strings(&["223.5.5.5", "119.29.29.29"]),
+                nameserver: strings(&CN_NAMESERVERS),
+                fallback: strings(&FOREIGN_NAMESERVERS),
+                fallback_filter: Some(FallbackFilter {
+                    geoip: true,
+                    geoip_code: "CN".to_string(),
+                }),
+                nameserver_policy,
+            },
+        }
+    }
+}
+
+impl std::str::FromStr for DnsMode {
+    type Err = ConvertError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fake-ip" => Ok(DnsMode::FakeIp),
+            "redir-host" => Ok(DnsMode::RedirHost),
+            _ => Err(ConvertError(format!(
+                "Invalid dns '{}', expected fake-ip or redir-host",
+                s
+            ))),
+        }
+    }
+}
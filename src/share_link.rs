@@ -0,0 +1,734 @@
+use crate::backend::{Backend, Rendered, convert_each, unsupported};
+use crate::converter::{ConvertError, ConvertOptions, OutputConfig};
+use crate::proxy::{Endpoint, OneOrMany, Proxy, Shadowsocks, Tls, Transport};
+use base64::Engine;
+use base64::alphabet;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig};
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC, percent_decode_str, utf8_percent_encode};
+use serde_json::json;
+use serde_yaml::{Mapping, Value};
+use url::form_urlencoded;
+use url::{Host, Url};
+
+/// Base64 engines that accept input with or without padding
+const LENIENT: GeneralPurposeConfig =
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent);
+const STANDARD: GeneralPurpose = GeneralPurpose::new(&alphabet::STANDARD, LENIENT);
+const URL_SAFE: GeneralPurpose = GeneralPurpose::new(&alphabet::URL_SAFE, LENIENT);
+
+/// Characters left as they are in link components: the RFC 3986 unreserved set
+const COMPONENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// Decode base64 text, accepting both the standard and URL-safe alphabets
+pub fn decode_base64(input: &str) -> Option<String> {
+    let cleaned: String = input.chars().filter(|c| !c.is_whitespace()).collect();
+    let bytes = STANDARD
+        .decode(&cleaned)
+        .or_else(|_| URL_SAFE.decode(&cleaned))
+        .ok()?;
+    String::from_utf8(bytes).ok()
+}
+
+/// Parse a subscription body made of share links, either plain or base64-encoded;
+/// lines that are not supported share links are skipped
+pub fn parse_link_list(content: &str) -> Vec<Value> {
+    let text = if content.contains("://") {
+        content.to_string()
+    } else {
+        match decode_base64(content) {
+            Some(decoded) => decoded,
+            None => return Vec::new(),
+        }
+    };
+
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .filter_map(parse_link)
+        .collect()
+}
+
+/// Parse a single share link into a Clash proxy
+pub fn parse_link(link: &str) -> Option<Value> {
+    let (scheme, _) = link.split_once("://")?;
+    let proxy = match scheme.to_ascii_lowercase().as_str() {
+        "ss" => parse_ss(link)?,
+        "vmess" => parse_vmess(link)?,
+        "trojan" => parse_trojan(link)?,
+        "vless" => parse_vless(link)?,
+        "hysteria2" | "hy2" => parse_hysteria2(link)?,
+        _ => return None,
+    };
+    Some(Value::Mapping(proxy))
+}
+
+/// Parse `ss://` links in both SIP002 and legacy (fully base64-encoded) form
+fn parse_ss(link: &str) -> Option<Mapping> {
+    let body = &link["ss://".len()..];
+    let (body, name) = split_fragment(body);
+
+    // Legacy form: ss://base64(method:password@host:port)#name
+    if !body.contains('@') {
+        let decoded = decode_base64(body.trim_end_matches('/'))?;
+        let (user_info, host_port) = decoded.rsplit_once('@')?;
+        let (cipher, password) = user_info.split_once(':')?;
+        let (server, port) = split_host_port(host_port)?;
+        let mut proxy = new_proxy(name.as_deref(), "ss", &server, port);
+        insert(&mut proxy, "cipher", cipher);
+        insert(&mut proxy, "password", password);
+        insert(&mut proxy, "udp", true);
+        return Some(proxy);
+    }
+
+    let url = Url::parse(link).ok()?;
+    let user_info = decode(url.username());
+    // SIP002 userinfo is base64(method:password), except for 2022 ciphers which use plain text
+    let (cipher, password) = match url.password() {
+        Some(password) => (user_info, decode(password)),
+        None => {
+            let decoded = decode_base64(&user_info).unwrap_or(user_info);
+            let (cipher, password) = decoded.split_once(':')?;
+            (cipher.to_string(), password.to_string())
+        }
+    };
+
+    let (server, port) = host_and_port(&url)?;
+    let mut proxy = new_proxy(name.as_deref(), "ss", &server, port);
+    insert(&mut proxy, "cipher", cipher);
+    insert(&mut proxy, "password", password);
+    insert(&mut proxy, "udp", true);
+
+    if let Some(plugin) = query_param(&url, "plugin") {
+        apply_ss_plugin(&mut proxy, &plugin);
+    }
+
+    Some(proxy)
+}
+
+/// Translate a SIP003 plugin string such as `obfs-local;obfs=http;obfs-host=example.com`
+fn apply_ss_plugin(proxy: &mut Mapping, plugin: &str) {
+    let mut parts = plugin.split(';');
+    let plugin_name = parts.next().unwrap_or_default();
+    let options: Vec<(&str, &str)> = parts
+        .map(|part| part.split_once('=').unwrap_or((part, "")))
+        .collect();
+
+    let mut opts = Mapping::new();
+    match plugin_name {
+        "obfs-local" | "simple-obfs" => {
+            insert(proxy, "plugin", "obfs");
+            for (key, value) in options {
+                match key {
+                    "obfs" => insert(&mut opts, "mode", value),
+                    "obfs-host" => insert(&mut opts, "host", value),
+                    _ => {}
+                }
+            }
+        }
+        "v2ray-plugin" => {
+            insert(proxy, "plugin", "v2ray-plugin");
+            insert(&mut opts, "mode", "websocket");
+            for (key, value) in options {
+                match key {
+                    "mode" => insert(&mut opts, "mode", value),
+                    "host" => insert(&mut opts, "host", value),
+                    "path" => insert(&mut opts, "path", value),
+                    "tls" => insert(&mut opts, "tls", true),
+                    "mux" => insert(&mut opts, "mux", value != "0"),
+                    _ => {}
+                }
+            }
+        }
+        _ => return,
+    }
+    insert(proxy, "plugin-opts", opts);
+}
+
+/// Parse `vmess://` links carrying a base64-encoded v2rayN JSON object
+fn parse_vmess(link: &str) -> Option<Mapping> {
+    let body = &link["vmess://".len()..];
+    let (body, _) = split_fragment(body);
+    let json: serde_json::Value = serde_json::from_str(&decode_base64(body)?).ok()?;
+
+    let field = |key: &str| -> Option<String> {
+        match json.get(key)? {
+            serde_json::Value::String(s) if !s.is_empty() => Some(s.clone()),
+            serde_json::Value::Number(n) => Some(n.to_string()),
+            _ => None,
+        }
+    };
+
+    let server = field("add")?;
+    let port = field("port")?.parse().ok()?;
+    let mut proxy = new_proxy(field("ps").as_deref(), "vmess", &server, port);
+    insert(&mut proxy, "uuid", field("id")?);
+    let alter_id: u64 = field("aid").and_then(|aid| aid.parse().ok()).unwrap_or(0);
+    insert(&mut proxy, "alterId", alter_id);
+    insert(
+        &mut proxy,
+        "cipher",
+        field("scy").unwrap_or_else(|| "auto".to_string()),
+    );
+    insert(&mut proxy, "udp", true);
+
+    if field("tls").is_some_and(|tls| tls == "tls") {
+        insert(&mut proxy, "tls", true);
+        if let Some(sni) = field("sni") {
+            insert(&mut proxy, "servername", sni);
+        }
+        if let Some(alpn) = field("alpn") {
+            insert(&mut proxy, "alpn", split_list(&alpn));
+        }
+        if let Some(fp) = field("fp") {
+            insert(&mut proxy, "client-fingerprint", fp);
+        }
+    }
+
+    let network = field("net").unwrap_or_else(|| "tcp".to_string());
+    let header_type = field("type");
+    apply_transport(
+        &mut proxy,
+        &network,
+        field("host").as_deref(),
+        field("path").as_deref(),
+        header_type.as_deref(),
+    );
+
+    Some(proxy)
+}
+
+/// Parse `trojan://password@host:port?...#name` links
+fn parse_trojan(link: &str) -> Option<Mapping> {
+    let url = Url::parse(link).ok()?;
+    let (server, port) = host_and_port(&url)?;
+    let name = url.fragment().map(decode);
+    let mut proxy = new_proxy(name.as_deref(), "trojan", &server, port);
+    insert(&mut proxy, "password", decode(url.username()));
+    insert(&mut proxy, "udp", true);
+
+    if let Some(sni) = query_param(&url, "sni").or_else(|| query_param(&url, "peer")) {
+        insert(&mut proxy, "sni", sni);
+    }
+    apply_tls_params(&mut proxy, &url);
+    apply_url_transport(&mut proxy, &url);
+
+    Some(proxy)
+}
+
+/// Parse `vless://uuid@host:port?...#name` links, including REALITY parameters
+fn parse_vless(link: &str) -> Option<Mapping> {
+    let url = Url::parse(link).ok()?;
+    let (server, port) = host_and_port(&url)?;
+    let name = url.fragment().map(decode);
+    let mut proxy = new_proxy(name.as_deref(), "vless", &server, port);
+    insert(&mut proxy, "uuid", decode(url.username()));
+    insert(&mut proxy, "udp", true);
+
+    let security = query_param(&url, "security").unwrap_or_default();
+    if security == "tls" || security == "reality" {
+        insert(&mut proxy, "tls", true);
+        if let Some(sni) = query_param(&url, "sni") {
+            insert(&mut proxy, "servername", sni);
+        }
+    }
+    if security == "reality" {
+        let mut reality = Mapping::new();
+        if let Some(public_key) = query_param(&url, "pbk") {
+            insert(&mut reality, "public-key", public_key);
+        }
+        if let Some(short_id) = query_param(&url, "sid") {
+            insert(&mut reality, "short-id", short_id);
+        }
+        insert(&mut proxy, "reality-opts", reality);
+    }
+    if let Some(flow) = query_param(&url, "flow") {
+        insert(&mut proxy, "flow", flow);
+    }
+    apply_tls_params(&mut proxy, &url);
+    apply_url_transport(&mut proxy, &url);
+
+    Some(proxy)
+}
+
+/// Parse `hysteria2://auth@host:port/?...#name` links (`hy2://` as well)
+fn parse_hysteria2(link: &str) -> Option<Mapping> {
+    let url = Url::parse(link).ok()?;
+    let (server, port) = host_and_port(&url)?;
+    let name = url.fragment().map(decode);
+    let mut proxy = new_proxy(name.as_deref(), "hysteria2", &server, port);
+    // The auth may be written as `user:pass`, which is sent as one string
+    let password = match url.password() {
+        Some(password) => format!("{}:{}", decode(url.username()), decode(password)),
+        None => decode(url.username()),
+    };
+    insert(&mut proxy, "password", password);
+    insert(&mut proxy, "udp", true);
+
+    if let Some(sni) = query_param(&url, "sni") {
+        insert(&mut proxy, "sni", sni);
+    }
+    if let Some(obfs) = query_param(&url, "obfs") {
+        insert(&mut proxy, "obfs", obfs);
+        if let Some(obfs_password) = query_param(&url, "obfs-password") {
+            insert(&mut proxy, "obfs-password", obfs_password);
+        }
+    }
+    if let Some(pin) = query_param(&url, "pinSHA256") {
+        insert(&mut proxy, "fingerprint", pin);
+    }
+    if query_param(&url, "insecure").is_some_and(|v| v == "1" || v == "true") {
+        insert(&mut proxy, "skip-cert-verify", true);
+    }
+
+    Some(proxy)
+}
+
+/// Common TLS query parameters shared by trojan and vless links
+fn apply_tls_params(proxy: &mut Mapping, url: &Url) {
+    if let Some(fp) = query_param(url, "fp") {
+        insert(proxy, "client-fingerprint", fp);
+    }
+    if let Some(alpn) = query_param(url, "alpn") {
+        insert(proxy, "alpn", split_list(&alpn));
+    }
+    if query_param(url, "allowInsecure").is_some_and(|v| v == "1" || v == "true") {
+        insert(proxy, "skip-cert-verify", true);
+    }
+}
+
+/// Read the transport from the `type`/`host`/`path`/`serviceName` query parameters
+fn apply_url_transport(proxy: &mut Mapping, url: &Url) {
+    let network = query_param(url, "type").unwrap_or_else(|| "tcp".to_string());
+    let path = query_param(url, "path").or_else(|| query_param(url, "serviceName"));
+    let header_type = query_param(url, "headerType");
+    apply_transport(
+        proxy,
+        &network,
+        query_param(url, "host").as_deref(),
+        path.as_deref(),
+        header_type.as_deref(),
+    );
+}
+
+/// Translate a v2ray transport into Clash `network` and `*-opts` fields (`path` is the grpc service name)
+fn apply_transport(
+    proxy: &mut Mapping,
+    network: &str,
+    host: Option<&str>,
+    path: Option<&str>,
+    header_type: Option<&str>,
+) {
+    match network {
+        "ws" | "websocket" => {
+            insert(proxy, "network", "ws");
+            let mut opts = Mapping::new();
+            if let Some(path) = path {
+                insert(&mut opts, "path", path);
+            }
+            if let Some(host) = host {
+                let mut headers = Mapping::new();
+                insert(&mut headers, "Host", host);
+                insert(&mut opts, "headers", headers);
+            }
+            insert(proxy, "ws-opts", opts);
+        }
+        "grpc" => {
+            insert(proxy, "network", "grpc");
+            let mut opts = Mapping::new();
+            if let Some(service_name) = path {
+                insert(&mut opts, "grpc-service-name", service_name);
+            }
+            insert(proxy, "grpc-opts", opts);
+        }
+        "h2" | "http" => {
+            insert(proxy, "network", "h2");
+            let mut opts = Mapping::new();
+            if let Some(host) = host {
+                insert(&mut opts, "host", split_list(host));
+            }
+            if let Some(path) = path {
+                insert(&mut opts, "path", path);
+            }
+            insert(proxy, "h2-opts", opts);
+        }
+        "tcp" if header_type == Some("http") => {
+            insert(proxy, "network", "http");
+            let mut opts = Mapping::new();
+            if let Some(path) = path {
+                insert(&mut opts, "path", split_list(path));
+            }
+            if let Some(host) = host {
+                let mut headers = Mapping::new();
+                insert(&mut headers, "Host", split_list(host));
+                insert(&mut opts, "headers", headers);
+            }
+            insert(proxy, "http-opts", opts);
+        }
+        _ => {}
+    }
+}
+
+/// Create a proxy map with the fields every Clash proxy has
+fn new_proxy(name: Option<&str>, proxy_type: &str, server: &str, port: u16) -> Mapping {
+    let name = match name {
+        Some(name) if !name.trim().is_empty() => name.trim().to_string(),
+        _ => format!("{}:{}", server, port),
+    };
+    let mut proxy = Mapping::new();
+    insert(&mut proxy, "name", name);
+    insert(&mut proxy, "type", proxy_type);
+    insert(&mut proxy, "server", server);
+    insert(&mut proxy, "port", port);
+    proxy
+}
+
+fn insert(map: &mut Mapping, key: &str, value: impl Into<Value>) {
+    map.insert(Value::String(key.to_string()), value.into());
+}
+
+fn decode(input: &str) -> String {
+    percent_decode_str(input).decode_utf8_lossy().into_owned()
+}
+
+fn split_list(input: &str) -> Vec<Value> {
+    input
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| Value::String(s.to_string()))
+        .collect()
+}
+
+/// Split `body#name` and percent-decode the name
+fn split_fragment(body: &str) -> (&str, Option<String>) {
+    match body.split_once('#') {
+        Some((body, name)) => (body, Some(decode(name))),
+        None => (body, None),
+    }
+}
+
+/// Split `host:port`, accepting bracketed IPv6 literals
+fn split_host_port(input: &str) -> Option<(String, u16)> {
+    let (host, port) = input.trim_end_matches('/').rsplit_once(':')?;
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    Some((host.to_string(), port.parse().ok()?))
+}
+
+fn host_and_port(url: &Url) -> Option<(String, u16)> {
+    let host = match url.host()? {
+        Host::Domain(domain) => decode(domain),
+        Host::Ipv4(ip) => ip.to_string(),
+        Host::Ipv6(ip) => ip.to_string(),
+    };
+    Some((host, url.port()?))
+}
+
+fn query_param(url: &Url, key: &str) -> Option<String> {
+    url.query_pairs()
+        .find(|(k, _)| k == key)
+        .map(|(_, v)| v.into_owned())
+        .filter(|v| !v.is_empty())
+}
+
+/// Base64-encoded share-link list, the format of most link subscriptions
+pub struct ShareLinks;
+
+impl Backend for ShareLinks {
+    fn render(
+        &self,
+        output: &OutputConfig,
+        _options: &ConvertOptions,
+    ) -> Result<Rendered, ConvertError> {
+        let (links, dropped) = convert_each(&output.proxies, to_link);
+        let links: Vec<String> = links.into_iter().map(|(_, link)| link).collect();
+        let content = STANDARD.encode(links.join("\n"));
+        Ok(Rendered { content, dropped })
+    }
+}
+
+/// Serialize a Clash proxy into a share link, or the reason it has none
+pub fn to_link(proxy: &Proxy) -> Result<String, String> {
+    match proxy {
+        Proxy::Shadowsocks(ss) => {
+            // 2022 ciphers carry the key in plain text, the others base64(method:password)
+            let user_info = if ss.cipher.starts_with("2022-") {
+                format!("{}:{}", encode(&ss.cipher), encode(&ss.password))
+            } else {
+                URL_SAFE_NO_PAD.encode(format!("{}:{}", ss.cipher, ss.password))
+            };
+            let mut params = Vec::new();
+            if let Some(plugin) = ss_plugin(ss)? {
+                params.push(("plugin", plugin));
+            }
+            Ok(url_link("ss", &user_info, &ss.endpoint, "/", &params))
+        }
+        Proxy::Vmess(vmess) => {
+            let transport = V2rayTransport::of(&vmess.transport)?;
+            let tls = &vmess.tls;
+            let tls_field = |value: &Option<String>| match tls.enabled() {
+                true => value.clone().unwrap_or_default(),
+                false => String::new(),
+            };
+            let alpn = match tls.enabled() {
+                true => tls.alpn().join(","),
+                false => String::new(),
+            };
+            // v2rayN JSON, with every value as a string
+            let json = json!({
+                "v": "2",
+                "ps": vmess.endpoint.name,
+                "add": vmess.endpoint.server,
+                "port": vmess.endpoint.port.to_string(),
+                "id": vmess.uuid,
+                "aid": vmess.alter_id.unwrap_or(0).to_string(),
+                "scy": vmess.cipher.as_deref().unwrap_or("auto"),
+                "net": transport.network,
+                "type": transport.header_type.unwrap_or("none".to_string()),
+                "host": transport.host.unwrap_or_default(),
+                "path": transport.path.unwrap_or_default(),
+                "tls": if tls.enabled() { "tls" } else { "" },
+                "sni": tls_field(&tls.servername),
+                "alpn": alpn,
+                "fp": tls_field(&tls.client_fingerprint),
+            });
+            Ok(format!("vmess://{}", STANDARD.encode(json.to_string())))
+        }
+        Proxy::Trojan(trojan) => {
+            let mut params = vec![("security", "tls".to_string())];
+            if let Some(sni) = &trojan.tls.sni {
+                params.push(("sni", sni.clone()));
+            }
+            push_tls_params(&trojan.tls, &mut params);
+            V2rayTransport::of(&trojan.transport)?.push_params(&mut params);
+            let user_info = encode(&trojan.password);
+            Ok(url_link(
+                "trojan",
+                &user_info,
+                &trojan.endpoint,
+                "",
+                &params,
+            ))
+        }
+        Proxy::Vless(vless) => {
+            let security = match (&vless.reality_opts, vless.tls.enabled()) {
+                (Some(_), _) => "reality",
+                (None, true) => "tls",
+                (None, false) => "none",
+            };
+            let mut params = vec![
+                ("encryption", "none".to_string()),
+                ("security", security.to_string()),
+            ];
+            if security != "none"
+                && let Some(sni) = &vless.tls.servername
+            {
+                params.push(("sni", sni.clone()));
+            }
+            if let Some(reality) = &vless.reality_opts {
+                if let Some(public_key) = &reality.public_key {
+                    params.push(("pbk", public_key.clone()));
+                }
+                if let Some(short_id) = &reality.short_id {
+                    params.push(("sid", short_id.clone()));
+                }
+            }
+            if let Some(flow) = &vless.flow {
+                params.push(("flow", flow.clone()));
+            }
+            push_tls_params(&vless.tls, &mut params);
+            V2rayTransport::of(&vless.transport)?.push_params(&mut params);
+            let user_info = encode(&vless.uuid);
+            Ok(url_link("vless", &user_info, &vless.endpoint, "", &params))
+        }
+        Proxy::Hysteria2(hy2) => {
+            let mut params = Vec::new();
+            if let Some(sni) = &hy2.tls.sni {
+                params.push(("sni", sni.clone()));
+            }
+            if let Some(obfs) = &hy2.obfs {
+                params.push(("obfs", obfs.clone()));
+                if let Some(obfs_password) = &hy2.obfs_password {
+                    params.push(("obfs-password", obfs_password.clone()));
+                }
+            }
+            if let Some(pin) = &hy2.tls.fingerprint {
+                params.push(("pinSHA256", pin.clone()));
+            }
+            if hy2.tls.insecure() {
+                params.push(("insecure", "1".to_string()));
+            }
+            let user_info = encode(&hy2.password);
+            Ok(url_link(
+                "hysteria2",
+                &user_info,
+                &hy2.endpoint,
+                "/",
+                &params,
+            ))
+        }
+        _ => Err(unsupported(proxy)),
+    }
+}
+
+/// SIP003 plugin string for the `plugin` parameter of `ss://` links
+fn ss_plugin(ss: &Shadowsocks) -> Result<Option<String>, String> {
+    let Some(plugin) = &ss.plugin else {
+        return Ok(None);
+    };
+    let opts = ss.plugin_opts.clone().unwrap_or_default();
+    let mut parts = Vec::new();
+    match plugin.as_str() {
+        "obfs" => {
+            parts.push("obfs-local".to_string());
+            if let Some(mode) = &opts.mode {
+                parts.push(format!("obfs={}", mode));
+            }
+            if let Some(host) = &opts.host {
+                parts.push(format!("obfs-host={}", host));
+            }
+        }
+        "v2ray-plugin" => {
+            parts.push("v2ray-plugin".to_string());
+            parts.push(format!(
+                "mode={}",
+                opts.mode.as_deref().unwrap_or("websocket")
+            ));
+            if let Some(host) = &opts.host {
+                parts.push(format!("host={}", host));
+            }
+            if let Some(path) = &opts.path {
+                parts.push(format!("path={}", path));
+            }
+            if opts.tls == Some(true) {
+                parts.push("tls".to_string());
+            }
+            if let Some(mux) = opts.mux {
+                parts.push(format!("mux={}", if mux { 1 } else { 0 }));
+            }
+        }
+        _ => return Err(format!("unsupported plugin '{}'", plugin)),
+    }
+    Ok(Some(parts.join(";")))
+}
+
+/// TLS query parameters read back by [`apply_tls_params`]
+fn push_tls_params(tls: &Tls, params: &mut Vec<(&str, String)>) {
+    if let Some(fp) = &tls.client_fingerprint {
+        params.push(("fp", fp.clone()));
+    }
+    let alpn = tls.alpn();
+    if !alpn.is_empty() {
+        params.push(("alpn", alpn.join(",")));
+    }
+    if tls.insecure() {
+        params.push(("allowInsecure", "1".to_string()));
+    }
+}
+
+/// A Clash transport in v2ray terms, the inverse of [`apply_transport`]
+struct V2rayTransport {
+    network: String,
+    header_type: Option<String>,
+    host: Option<String>,
+    /// Path, or the service name for grpc
+    path: Option<String>,
+}
+
+impl V2rayTransport {
+    fn of(transport: &Transport) -> Result<Self, String> {
+        let joined = |items: Vec<String>| Some(items.join(",")).filter(|s| !s.is_empty());
+        let v2ray = |network: &str, host, path| V2rayTransport {
+            network: network.to_string(),
+            header_type: None,
+            host,
+            path,
+        };
+
+        match transport.network() {
+            "tcp" => Ok(v2ray("tcp", None, None)),
+            "ws" => {
+                let ws = transport.ws_opts.clone().unwrap_or_default();
+                Ok(v2ray("ws", ws.host().map(str::to_string), ws.path))
+            }
+            "grpc" => {
+                let grpc = transport.grpc_opts.clone().unwrap_or_default();
+                Ok(v2ray("grpc", None, grpc.grpc_service_name))
+            }
+            "h2" => {
+                let h2 = transport.h2_opts.clone().unwrap_or_default();
+                let hosts = h2.host.as_ref().map(OneOrMany::to_vec).unwrap_or_default();
+                Ok(v2ray("h2", joined(hosts), h2.path))
+            }
+            "http" => {
+                let http = transport.http_opts.clone().unwrap_or_default();
+                let paths = http
+                    .path
+                    .as_ref()
+                    .map(OneOrMany::to_vec)
+                    .unwrap_or_default();
+                Ok(V2rayTransport {
+                    header_type: Some("http".to_string()),
+                    ..v2ray("tcp", joined(http.hosts()), joined(paths))
+                })
+            }
+            network => Err(format!("unsupported transport '{}'", network)),
+        }
+    }
+
+    /// Query parameters read back by [`apply_url_transport`]
+    fn push_params(self, params: &mut Vec<(&str, String)>) {
+        let path_key = if self.network == "grpc" {
+            "serviceName"
+        } else {
+            "path"
+        };
+        params.push(("type", self.network));
+        if let Some(header_type) = self.header_type {
+            params.push(("headerType", header_type));
+        }
+        if let Some(host) = self.host {
+            params.push(("host", host));
+        }
+        if let Some(path) = self.path {
+            params.push((path_key, path));
+        }
+    }
+}
+
+/// Build `scheme://user_info@host:port<path>?params#name`; `user_info` must already be encoded
+fn url_link(
+    scheme: &str,
+    user_info: &str,
+    endpoint: &Endpoint,
+    path: &str,
+    params: &[(&str, String)],
+) -> String {
+    let server = &endpoint.server;
+    let host = if server.contains(':') {
+        format!("[{}]", server)
+    } else {
+        server.to_string()
+    };
+    let mut link = format!("{}://{}@{}:{}", scheme, user_info, host, endpoint.port);
+    if !params.is_empty() {
+        let query = form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(params.iter().map(|(k, v)| (*k, v.as_str())))
+            .finish();
+        link.push_str(path);
+        link.push('?');
+        link.push_str(&query);
+    }
+    link.push('#');
+    link.push_str(&encode(&endpoint.name));
+    link
+}
+
+/// Percent-encode a link component, the inverse of [`decode`]
+fn encode(input: &str) -> String {
+    utf8_percent_encode(input, COMPONENT).to_string()
+}
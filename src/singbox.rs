@@ -0,0 +1,341 @@
+use crate::backend::{Backend, DIRECT, Rendered, Rule, convert_each, resolve_groups, unsupported};
+use crate::converter::{ConvertError, ConvertOptions, OutputConfig, ProxyGroup};
+use crate::proxy::{OneOrMany, Proxy, RealityOpts, Shadowsocks, Tls, Transport};
+use serde_json::{Map, Value as Json, json};
+use std::collections::HashSet;
+
+/// Remote binary rule sets matching the geodata used by mihomo
+const GEOSITE_RULE_SET_URL: &str =
+    "https://raw.githubusercontent.com/MetaCubeX/meta-rules-dat/sing/geo/geosite";
+const GEOIP_RULE_SET_URL: &str =
+    "https://raw.githubusercontent.com/MetaCubeX/meta-rules-dat/sing/geo/geoip";
+
+/// sing-box JSON with outbounds and route
+pub struct SingBox;
+
+impl Backend for SingBox {
+    fn render(
+        &self,
+        output: &OutputConfig,
+        _options: &ConvertOptions,
+    ) -> Result<Rendered, ConvertError> {
+        let (proxies, dropped) = convert_each(&output.proxies, outbound);
+        let proxy_names: Vec<String> = proxies.iter().map(|(name, _)| name.clone()).collect();
+
+        let mut outbounds = group_outbounds(&output.proxy_groups, &proxy_names)?;
+        outbounds.extend(proxies.into_iter().map(|(_, proxy)| proxy));
+        // 直接连接 points at DIRECT like in Clash
+        outbounds.push(json!({ "type": "direct", "tag": DIRECT }));
+
+        let config = json!({
+            "outbounds": outbounds,
+            "route": route(&output.rules),
+        });
+        let content = serde_json::to_string_pretty(&config)
+            .map_err(|e| ConvertError(format!("Failed to serialize JSON: {}", e)))?;
+        Ok(Rendered { content, dropped })
+    }
+}
+
+/// Convert a Clash proxy into a sing-box outbound, or the reason it is not supported
+fn outbound(proxy: &Proxy) -> Result<Json, String> {
+    let (sing_type, endpoint) = match proxy {
+        Proxy::Shadowsocks(p) => ("shadowsocks", &p.endpoint),
+        Proxy::Vmess(p) => ("vmess", &p.endpoint),
+        Proxy::Trojan(p) => ("trojan", &p.endpoint),
+        Proxy::Vless(p) => ("vless", &p.endpoint),
+        Proxy::Hysteria2(p) => ("hysteria2", &p.endpoint),
+        Proxy::Tuic(p) => ("tuic", &p.endpoint),
+        _ => return Err(unsupported(proxy)),
+    };
+
+    let mut out = Map::new();
+    out.insert("type".to_string(), json!(sing_type));
+    out.insert("tag".to_string(), json!(endpoint.name));
+    out.insert("server".to_string(), json!(endpoint.server));
+    out.insert("server_port".to_string(), json!(endpoint.port));
+
+    let mut insert = |key: &str, value: Option<Json>| {
+        if let Some(value) = value {
+            out.insert(key.to_string(), value);
+        }
+    };
+
+    match proxy {
+        Proxy::Shadowsocks(ss) => {
+            insert("method", Some(json!(ss.cipher)));
+            insert("password", Some(json!(ss.password)));
+            if let Some((plugin, opts)) = shadowsocks_plugin(ss)? {
+                insert("plugin", Some(json!(plugin)));
+                insert("plugin_opts", Some(json!(opts)));
+            }
+        }
+        Proxy::Vmess(vmess) => {
+            insert("uuid", Some(json!(vmess.uuid)));
+            insert(
+                "security",
+                Some(json!(vmess.cipher.as_deref().unwrap_or("auto"))),
+            );
+            insert("alter_id", Some(json!(vmess.alter_id.unwrap_or(0))));
+            if vmess.tls.enabled() {
+                insert("tls", Some(tls(&vmess.tls, None)));
+            }
+            insert("transport", transport(&vmess.transport));
+        }
+        Proxy::Trojan(trojan) => {
+            insert("password", Some(json!(trojan.password)));
+            insert("tls", Some(tls(&trojan.tls, None)));
+            insert("transport", transport(&trojan.transport));
+        }
+        Proxy::Vless(vless) => {
+            insert("uuid", Some(json!(vless.uuid)));
+            insert("flow", vless.flow.as_ref().map(|flow| json!(flow)));
+            if vless.tls_enabled() {
+                insert("tls", Some(tls(&vless.tls, vless.reality_opts.as_ref())));
+            }
+            insert("transport", transport(&vless.transport));
+        }
+        Proxy::Hysteria2(hy2) => {
+            insert("password", Some(json!(hy2.password)));
+            insert("up_mbps", hy2.up_mbps().map(Json::from));
+            insert("down_mbps", hy2.down_mbps().map(Json::from));
+            if let Some(obfs) = &hy2.obfs {
+                insert(
+                    "obfs",
+                    Some(json!({
+                        "type": obfs,
+                        "password": hy2.obfs_password.clone().unwrap_or_default(),
+                    })),
+                );
+            }
+            insert("tls", Some(tls(&hy2.tls, None)));
+        }
+        Proxy::Tuic(tuic) => {
+            // sing-box has no token authentication
+            let (Some(uuid), Some(password)) = (&tuic.uuid, &tuic.password) else {
+                return Err("only TUIC v5 is supported".to_string());
+            };
+            insert("uuid", Some(json!(uuid)));
+            insert("password", Some(json!(password)));
+            insert(
+                "congestion_control",
+                tuic.congestion_controller.as_ref().map(|c| json!(c)),
+            );
+            insert(
+                "udp_relay_mode",
+                tuic.udp_relay_mode.as_ref().map(|mode| json!(mode)),
+            );
+            insert("tls", Some(tls(&tuic.tls, None)));
+        }
+        _ => {}
+    }
+
+    Ok(Json::Object(out))
+}
+
+/// Shadowsocks plugin name and SIP003 options string
+fn shadowsocks_plugin(ss: &Shadowsocks) -> Result<Option<(String, String)>, String> {
+    let Some(plugin) = &ss.plugin else {
+        return Ok(None);
+    };
+    let opts = ss.plugin_opts.clone().unwrap_or_default();
+
+    match plugin.as_str() {
+        "obfs" => {
+            let mut parts = vec![format!("obfs={}", opts.mode.as_deref().unwrap_or("http"))];
+            if let Some(host) = &opts.host {
+                parts.push(format!("obfs-host={}", host));
+            }
+            Ok(Some(("obfs-local".to_string(), parts.join(";"))))
+        }
+        "v2ray-plugin" => {
+            let mut parts = vec![format!(
+                "mode={}",
+                opts.mode.as_deref().unwrap_or("websocket")
+            )];
+            if let Some(host) = &opts.host {
+                parts.push(format!("host={}", host));
+            }
+            if let Some(path) = &opts.path {
+                parts.push(format!("path={}", path));
+            }
+            if opts.tls == Some(true) {
+                parts.push("tls".to_string());
+            }
+            Ok(Some(("v2ray-plugin".to_string(), parts.join(";"))))
+        }
+        _ => Err(format!("unsupported plugin '{}'", plugin)),
+    }
+}
+
+/// TLS settings, with REALITY for vless
+fn tls(settings: &Tls, reality: Option<&RealityOpts>) -> Json {
+    let mut tls = Map::new();
+    tls.insert("enabled".to_string(), json!(true));
+    if let Some(server_name) = settings.server_name() {
+        tls.insert("server_name".to_string(), json!(server_name));
+    }
+    if settings.insecure() {
+        tls.insert("insecure".to_string(), json!(true));
+    }
+    let alpn = settings.alpn();
+    if !alpn.is_empty() {
+        tls.insert("alpn".to_string(), json!(alpn));
+    }
+    if let Some(fingerprint) = &settings.client_fingerprint {
+        tls.insert(
+            "utls".to_string(),
+            json!({ "enabled": true, "fingerprint": fingerprint }),
+        );
+    }
+    if let Some(reality) = reality {
+        tls.insert(
+            "reality".to_string(),
+            json!({
+                "enabled": true,
+                "public_key": reality.public_key.clone().unwrap_or_default(),
+                "short_id": reality.short_id.clone().unwrap_or_default(),
+            }),
+        );
+    }
+    Json::Object(tls)
+}
+
+/// V2Ray transport from the Clash `network` and its options
+fn transport(transport: &Transport) -> Option<Json> {
+    match transport.network() {
+        "ws" => {
+            let ws = transport.ws_opts.clone().unwrap_or_default();
+            let mut out = Map::new();
+            out.insert("type".to_string(), json!("ws"));
+            if let Some(path) = &ws.path {
+                out.insert("path".to_string(), json!(path));
+            }
+            if let Some(host) = ws.host() {
+                out.insert("headers".to_string(), json!({ "Host": host }));
+            }
+            Some(Json::Object(out))
+        }
+        "grpc" => {
+            let grpc = transport.grpc_opts.clone().unwrap_or_default();
+            Some(json!({
+                "type": "grpc",
+                "service_name": grpc.grpc_service_name.unwrap_or_default(),
+            }))
+        }
+        "h2" => {
+            let h2 = transport.h2_opts.clone().unwrap_or_default();
+            let hosts = h2.host.as_ref().map(OneOrMany::to_vec).unwrap_or_default();
+            Some(http_transport(hosts, h2.path))
+        }
+        "http" => {
+            let http = transport.http_opts.clone().unwrap_or_default();
+            let path = http
+                .path
+                .as_ref()
+                .and_then(|path| path.to_vec().into_iter().next());
+            Some(http_transport(http.hosts(), path))
+        }
+        _ => None,
+    }
+}
+
+/// sing-box `http` transport, used for both h2 and HTTP/1.1
+fn http_transport(hosts: Vec<String>, path: Option<String>) -> Json {
+    let mut out = Map::new();
+    out.insert("type".to_string(), json!("http"));
+    if !hosts.is_empty() {
+        out.insert("host".to_string(), json!(hosts));
+    }
+    if let Some(path) = path {
+        out.insert("path".to_string(), json!(path));
+    }
+    Json::Object(out)
+}
+
+/// Selector and urltest outbounds for the proxy groups
+fn group_outbounds(
+    groups: &[ProxyGroup],
+    proxy_names: &[String],
+) -> Result<Vec<Json>, ConvertError> {
+    let resolved = resolve_groups(groups, proxy_names)?;
+
+    Ok(resolved
+        .into_iter()
+        .map(|(group, members)| {
+            if group.group_type == "select" {
+                return json!({ "type": "selector", "tag": group.name, "outbounds": members });
+            }
+            // load-balance has no sing-box equivalent; every balanced group becomes urltest
+            let mut out = Map::new();
+            out.insert("type".to_string(), json!("urltest"));
+            out.insert("tag".to_string(), json!(group.name));
+            out.insert("outbounds".to_string(), json!(members));
+            if let Some(url) = &group.url {
+                out.insert("url".to_string(), json!(url));
+            }
+            if let Some(interval) = group.interval {
+                out.insert("interval".to_string(), json!(format!("{}s", interval)));
+            }
+            if let Some(tolerance) = group.tolerance {
+                out.insert("tolerance".to_string(), json!(tolerance));
+            }
+            Json::Object(out)
+        })
+        .collect())
+}
+
+/// Route rules with remote rule sets for the `GEOSITE` / `GEOIP` rules; `MATCH` becomes `final`
+fn route(rules: &[String]) -> Json {
+    let mut route_rules = vec![json!({ "action": "sniff" })];
+    let mut rule_sets: Vec<Json> = Vec::new();
+    let mut rule_set_tags: HashSet<String> = HashSet::new();
+    let mut final_outbound = None;
+
+    for rule in rules {
+        let (tag, url, target) = match Rule::parse(rule) {
+            Some(Rule::Match { target }) => {
+                final_outbound = Some(target.to_string());
+                continue;
+            }
+            Some(Rule::GeoSite { name, target }) => {
+                let name = name.to_lowercase();
+                (
+                    format!("geosite-{}", name),
+                    format!("{}/{}.srs", GEOSITE_RULE_SET_URL, name),
+                    target,
+                )
+            }
+            Some(Rule::GeoIp { code, target }) => {
+                let code = code.to_lowercase();
+                (
+                    format!("geoip-{}", code),
+                    format!("{}/{}.srs", GEOIP_RULE_SET_URL, code),
+                    target,
+                )
+            }
+            _ => continue,
+        };
+
+        route_rules.push(match target {
+            "REJECT" | "REJECT-DROP" => json!({ "rule_set": [tag], "action": "reject" }),
+            _ => json!({ "rule_set": [tag], "outbound": target }),
+        });
+        if rule_set_tags.insert(tag.clone()) {
+            rule_sets.push(json!({
+                "tag": tag,
+                "type": "remote",
+                "format": "binary",
+                "url": url,
+            }));
+        }
+    }
+
+    let mut route = Map::new();
+    route.insert("rules".to_string(), json!(route_rules));
+    route.insert("rule_set".to_string(), json!(rule_sets));
+    if let Some(final_outbound) = final_outbound {
+        route.insert("final".to_string(), json!(final_outbound));
+    }
+    Json::Object(route)
+}
@@ -0,0 +1,233 @@
+use crate::backend::{
+    Backend, Rendered, Rule, check_line, convert_each, dropped_comments, is_country_code,
+    resolve_groups, rule_list_url, section, unsupported,
+};
+use crate::converter::{ConvertError, ConvertOptions, OutputConfig, ProxyGroup};
+use crate::proxy::{Proxy, Tls, Transport};
+
+/// Surge profile with `[Proxy]`, `[Proxy Group]` and `[Rule]`
+pub struct Surge;
+
+impl Backend for Surge {
+    fn render(
+        &self,
+        output: &OutputConfig,
+        _options: &ConvertOptions,
+    ) -> Result<Rendered, ConvertError> {
+        let (proxies, dropped) = convert_each(&output.proxies, proxy_line);
+        let proxy_names: Vec<String> = proxies.iter().map(|(name, _)| name.clone()).collect();
+        let groups = resolve_groups(&output.proxy_groups, &proxy_names)?;
+
+        let proxy_lines: Vec<String> = proxies.into_iter().map(|(_, line)| line).collect();
+        let group_lines: Vec<String> = groups
+            .iter()
+            .map(|(group, members)| group_line(group, members))
+            .collect();
+        let rule_lines: Vec<String> = output.rules.iter().filter_map(|r| rule_line(r)).collect();
+
+        let content = [
+            dropped_comments(&dropped, "#"),
+            section("Proxy", &proxy_lines),
+            section("Proxy Group", &group_lines),
+            section("Rule", &rule_lines),
+        ]
+        .into_iter()
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n");
+        Ok(Rendered { content, dropped })
+    }
+}
+
+/// A `[Proxy]` line, or the reason the proxy is not supported
+fn proxy_line(proxy: &Proxy) -> Result<String, String> {
+    let mut params: Vec<String> = Vec::new();
+
+    let (surge_type, endpoint, udp) = match proxy {
+        Proxy::Shadowsocks(ss) => {
+            params.push(format!("encrypt-method={}", ss.cipher));
+            params.push(format!("password={}", ss.password));
+            match ss.plugin.as_deref() {
+                None => {}
+                Some("obfs") => {
+                    let opts = ss.plugin_opts.clone().unwrap_or_default();
+                    params.push(format!("obfs={}", opts.mode.as_deref().unwrap_or("http")));
+                    if let Some(host) = &opts.host {
+                        params.push(format!("obfs-host={}", host));
+                    }
+                }
+                Some(plugin) => return Err(format!("unsupported plugin '{}'", plugin)),
+            }
+            ("ss", &ss.endpoint, ss.udp)
+        }
+        Proxy::Vmess(vmess) => {
+            params.push(format!("username={}", vmess.uuid));
+            if vmess.aead() {
+                params.push("vmess-aead=true".to_string());
+            }
+            if vmess.tls.enabled() {
+                params.push("tls=true".to_string());
+                push_tls(&vmess.tls, &mut params);
+            }
+            push_transport(&vmess.transport, &mut params)?;
+            ("vmess", &vmess.endpoint, None)
+        }
+        Proxy::Trojan(trojan) => {
+            params.push(format!("password={}", trojan.password));
+            push_tls(&trojan.tls, &mut params);
+            push_transport(&trojan.transport, &mut params)?;
+            ("trojan", &trojan.endpoint, None)
+        }
+        Proxy::Hysteria2(hy2) => {
+            if hy2.obfs.is_some() {
+                return Err("obfs is not supported".to_string());
+            }
+            params.push(format!("password={}", hy2.password));
+            push_tls(&hy2.tls, &mut params);
+            if let Some(down) = hy2.down_mbps() {
+                params.push(format!("download-bandwidth={}", down));
+            }
+            ("hysteria2", &hy2.endpoint, None)
+        }
+        Proxy::Tuic(tuic) => {
+            let (Some(uuid), Some(password)) = (&tuic.uuid, &tuic.password) else {
+                return Err("only TUIC v5 is supported".to_string());
+            };
+            params.push(format!("uuid={}", uuid));
+            params.push(format!("password={}", password));
+            push_tls(&tuic.tls, &mut params);
+            let alpn = tuic.tls.alpn();
+            if !alpn.is_empty() {
+                params.push(format!("alpn={}", alpn.join(",")));
+            }
+            ("tuic-v5", &tuic.endpoint, None)
+        }
+        Proxy::Snell(snell) => {
+            params.push(format!("psk={}", snell.psk));
+            if let Some(version) = snell.version {
+                params.push(format!("version={}", version));
+            }
+            if let Some(opts) = &snell.obfs_opts {
+                if let Some(mode) = &opts.mode {
+                    params.push(format!("obfs={}", mode));
+                }
+                if let Some(host) = &opts.host {
+                    params.push(format!("obfs-host={}", host));
+                }
+            }
+            ("snell", &snell.endpoint, snell.udp)
+        }
+        // Credentials are positional
+        Proxy::Http(http) => {
+            push_credentials(&http.username, &http.password, &mut params);
+            if http.tls.enabled() {
+                push_tls(&http.tls, &mut params);
+                ("https", &http.endpoint, None)
+            } else {
+                ("http", &http.endpoint, None)
+            }
+        }
+        Proxy::Socks5(socks) => {
+            push_credentials(&socks.username, &socks.password, &mut params);
+            if socks.tls.enabled() {
+                push_tls(&socks.tls, &mut params);
+                ("socks5-tls", &socks.endpoint, None)
+            } else {
+                ("socks5", &socks.endpoint, socks.udp)
+            }
+        }
+        _ => return Err(unsupported(proxy)),
+    };
+
+    if udp == Some(true) {
+        params.push("udp-relay=true".to_string());
+    }
+
+    let fields: Vec<String> = [
+        surge_type.to_string(),
+        endpoint.server.clone(),
+        endpoint.port.to_string(),
+    ]
+    .into_iter()
+    .chain(params)
+    .collect();
+    check_line(&endpoint.name, &fields)?;
+    Ok(format!("{} = {}", endpoint.name, fields.join(", ")))
+}
+
+fn push_credentials(
+    username: &Option<String>,
+    password: &Option<String>,
+    params: &mut Vec<String>,
+) {
+    if let Some(username) = username {
+        params.push(username.clone());
+        params.push(password.clone().unwrap_or_default());
+    }
+}
+
+/// TLS parameters
+fn push_tls(tls: &Tls, params: &mut Vec<String>) {
+    if let Some(sni) = tls.server_name() {
+        params.push(format!("sni={}", sni));
+    }
+    if tls.insecure() {
+        params.push("skip-cert-verify=true".to_string());
+    }
+}
+
+/// WebSocket parameters; Surge supports no other transport
+fn push_transport(transport: &Transport, params: &mut Vec<String>) -> Result<(), String> {
+    match transport.network() {
+        "tcp" => Ok(()),
+        "ws" => {
+            let ws = transport.ws_opts.clone().unwrap_or_default();
+            params.push("ws=true".to_string());
+            if let Some(path) = &ws.path {
+                params.push(format!("ws-path={}", path));
+            }
+            if let Some(host) = ws.host() {
+                params.push(format!("ws-headers=Host:{}", host));
+            }
+            Ok(())
+        }
+        network => Err(format!("unsupported transport '{}'", network)),
+    }
+}
+
+/// A `[Proxy Group]` line
+fn group_line(group: &ProxyGroup, members: &[String]) -> String {
+    let mut fields = vec![group.group_type.clone()];
+    fields.extend(members.iter().cloned());
+    if group.group_type != "select" {
+        if let Some(url) = &group.url {
+            fields.push(format!("url={}", url));
+        }
+        if let Some(interval) = group.interval {
+            fields.push(format!("interval={}", interval));
+        }
+        if let Some(tolerance) = group.tolerance {
+            fields.push(format!("tolerance={}", tolerance));
+        }
+        // Keep a destination on the same proxy, like consistent-hashing and sticky-sessions
+        if group.group_type == "load-balance" && group.strategy.as_deref() != Some("round-robin") {
+            fields.push("persistent=true".to_string());
+        }
+    }
+    format!("{} = {}", group.name, fields.join(", "))
+}
+
+/// A `[Rule]` line; GEOSITE categories become remote rule lists
+fn rule_line(rule: &str) -> Option<String> {
+    match Rule::parse(rule)? {
+        Rule::GeoSite { name, target } => {
+            let url = rule_list_url("Surge", name)?;
+            Some(format!("RULE-SET,{},{}", url, target))
+        }
+        Rule::GeoIp { code, target } if is_country_code(code) => {
+            Some(format!("GEOIP,{},{}", code.to_uppercase(), target))
+        }
+        Rule::Match { target } => Some(format!("FINAL,{}", target)),
+        _ => None,
+    }
+}
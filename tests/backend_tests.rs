@@ -0,0 +1,311 @@
+//! Tests for the Surge, Loon and Quantumult X output
+//!
+//! Run with: cargo test
+
+use clash_sub::backend::Rendered;
+use clash_sub::converter::{ConvertOptions, parse_subscription, render_proxies};
+
+const INPUT: &str = r#"
+proxies:
+  - name: "香港-01"
+    type: ss
+    server: hk1.example.com
+    port: 8388
+    cipher: aes-128-gcm
+    password: secret
+    udp: true
+    plugin: obfs
+    plugin-opts:
+      mode: tls
+      host: bing.com
+  - name: "美国-01"
+    type: vmess
+    server: us1.example.com
+    port: 443
+    uuid: 11111111-1111-1111-1111-111111111111
+    alterId: 0
+    cipher: auto
+    tls: true
+    servername: us1.example.com
+    network: ws
+    ws-opts:
+      path: /ws
+      headers:
+        Host: us1.example.com
+  - name: "日本-01"
+    type: trojan
+    server: jp1.example.com
+    port: 443
+    password: pass
+    sni: jp1.example.com
+    skip-cert-verify: true
+  - name: "日本-02"
+    type: vmess
+    server: jp2.example.com
+    port: 443
+    uuid: 22222222-2222-2222-2222-222222222222
+    alterId: 0
+    cipher: auto
+    network: grpc
+    grpc-opts:
+      grpc-service-name: svc
+  - name: "新加坡-01"
+    type: snell
+    server: sg1.example.com
+    port: 443
+    psk: psk
+"#;
+
+fn render(target: &str, pairs: &[(&str, &str)]) -> Result<Rendered, String> {
+    render_input(INPUT, target, pairs)
+}
+
+fn render_input(input: &str, target: &str, pairs: &[(&str, &str)]) -> Result<Rendered, String> {
+    let query: Vec<(String, String)> = std::iter::once(("target", target))
+        .chain(pairs.iter().copied())
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    let options = ConvertOptions::from_query(&query).map_err(|e| e.to_string())?;
+    let proxies = parse_subscription(input).map_err(|e| e.to_string())?;
+    let conversion = render_proxies(proxies, &options).map_err(|e| e.to_string())?;
+    Ok(Rendered {
+        content: conversion.content,
+        dropped: conversion.report.dropped,
+    })
+}
+
+/// The lines of an INI section, without the header
+fn section<'a>(content: &'a str, name: &str) -> Vec<&'a str> {
+    let header = format!("[{}]", name);
+    content
+        .lines()
+        .skip_while(|line| *line != header)
+        .skip(1)
+        .take_while(|line| !line.is_empty())
+        .collect()
+}
+
+fn dropped_names(rendered: &Rendered) -> Vec<&str> {
+    rendered.dropped.iter().map(|d| d.name.as_str()).collect()
+}
+
+// ============================================================================
+// Tests for the Surge output
+// ============================================================================
+
+mod surge_tests {
+    use super::*;
+
+    #[test]
+    fn test_proxy_lines() {
+        let rendered = render("surge", &[]).unwrap();
+        let proxies = section(&rendered.content, "Proxy");
+
+        assert!(proxies.contains(
+            &"香港-01 = ss, hk1.example.com, 8388, encrypt-method=aes-128-gcm, password=secret, obfs=tls, obfs-host=bing.com, udp-relay=true"
+        ));
+        assert!(proxies.contains(
+            &"美国-01 = vmess, us1.example.com, 443, username=11111111-1111-1111-1111-111111111111, vmess-aead=true, tls=true, sni=us1.example.com, ws=true, ws-path=/ws, ws-headers=Host:us1.example.com"
+        ));
+        assert!(proxies.contains(&"新加坡-01 = snell, sg1.example.com, 443, psk=psk"));
+    }
+
+    #[test]
+    fn test_unsupported_transport_dropped() {
+        let rendered = render("surge", &[]).unwrap();
+
+        assert_eq!(dropped_names(&rendered), vec!["日本-02"]);
+        assert_eq!(rendered.dropped[0].reason, "unsupported transport 'grpc'");
+        assert!(
+            rendered
+                .content
+                .starts_with("# Dropped 日本-02: unsupported transport 'grpc'\n")
+        );
+        assert!(!rendered.content.contains("jp2.example.com"));
+    }
+
+    #[test]
+    fn test_groups_list_supported_members() {
+        let rendered = render("surge", &[]).unwrap();
+        let groups = section(&rendered.content, "Proxy Group");
+
+        assert!(groups.contains(&"日本负载组 = load-balance, 日本-01, url=http://www.gstatic.com/generate_204, interval=180, persistent=true"));
+        assert!(groups.contains(&"直接连接 = select, DIRECT"));
+    }
+
+    #[test]
+    fn test_rules() {
+        let rendered = render("surge", &[]).unwrap();
+        let rules = section(&rendered.content, "Rule");
+
+        assert!(rules.contains(&"RULE-SET,https://raw.githubusercontent.com/blackmatrix7/ios_rule_script/master/rule/Surge/China/China.list,直接连接"));
+        assert!(rules.contains(&"GEOIP,CN,直接连接"));
+        assert_eq!(rules.last(), Some(&"FINAL,默认流量"));
+    }
+}
+
+// ============================================================================
+// Tests for the Loon output
+// ============================================================================
+
+mod loon_tests {
+    use super::*;
+
+    #[test]
+    fn test_proxy_lines() {
+        let rendered = render("loon", &[]).unwrap();
+        let proxies = section(&rendered.content, "Proxy");
+
+        assert!(proxies.contains(
+            &"香港-01 = Shadowsocks,hk1.example.com,8388,aes-128-gcm,\"secret\",obfs-name=tls,obfs-host=bing.com,udp=true"
+        ));
+        assert!(proxies.contains(
+            &"日本-01 = trojan,jp1.example.com,443,\"pass\",transport=tcp,sni=jp1.example.com,skip-cert-verify=true"
+        ));
+    }
+
+    #[test]
+    fn test_unsupported_proxies_dropped() {
+        let rendered = render("loon", &[]).unwrap();
+
+        assert_eq!(dropped_names(&rendered), vec!["日本-02", "新加坡-01"]);
+        assert_eq!(rendered.dropped[1].reason, "unsupported type 'snell'");
+    }
+
+    #[test]
+    fn test_round_robin_algorithm() {
+        let rendered = render("loon", &[("strategy", "round-robin")]).unwrap();
+        let groups = section(&rendered.content, "Proxy Group");
+
+        assert!(groups.contains(&"美国负载组 = load-balance,美国-01,url=http://www.gstatic.com/generate_204,interval=180,algorithm=round-robin"));
+    }
+
+    #[test]
+    fn test_remote_rules() {
+        let rendered = render("loon", &[]).unwrap();
+        let remote = section(&rendered.content, "Remote Rule");
+        let rules = section(&rendered.content, "Rule");
+
+        assert!(remote.contains(&"https://raw.githubusercontent.com/blackmatrix7/ios_rule_script/master/rule/Loon/Lan/Lan.list, policy=直接连接, tag=private, enabled=true"));
+        assert_eq!(rules, vec!["GEOIP,CN,直接连接", "FINAL,默认流量"]);
+    }
+}
+
+// ============================================================================
+// Tests for the Quantumult X output
+// ============================================================================
+
+mod quantumultx_tests {
+    use super::*;
+
+    #[test]
+    fn test_server_lines() {
+        let rendered = render("quanx", &[]).unwrap();
+        let servers = section(&rendered.content, "server_local");
+
+        assert!(servers.contains(
+            &"shadowsocks=hk1.example.com:8388, method=aes-128-gcm, password=secret, obfs=tls, obfs-host=bing.com, udp-relay=true, tag=香港-01"
+        ));
+        assert!(servers.contains(
+            &"vmess=us1.example.com:443, method=chacha20-poly1305, password=11111111-1111-1111-1111-111111111111, obfs=wss, obfs-host=us1.example.com, obfs-uri=/ws, tls-host=us1.example.com, aead=true, tag=美国-01"
+        ));
+        assert!(servers.contains(
+            &"trojan=jp1.example.com:443, password=pass, over-tls=true, tls-host=jp1.example.com, tls-verification=false, tag=日本-01"
+        ));
+    }
+
+    #[test]
+    fn test_unsupported_proxies_dropped() {
+        let rendered = render("quanx", &[]).unwrap();
+
+        assert_eq!(dropped_names(&rendered), vec!["日本-02", "新加坡-01"]);
+        assert!(
+            rendered
+                .content
+                .contains("; Dropped 新加坡-01: unsupported type 'snell'\n")
+        );
+    }
+
+    #[test]
+    fn test_policies() {
+        let rendered =
+            render("quanx", &[("group_type", "url-test"), ("tolerance", "100")]).unwrap();
+        let policies = section(&rendered.content, "policy");
+
+        assert!(policies.contains(&"static=直接连接, direct"));
+        assert!(policies.contains(
+            &"url-latency-benchmark=美国负载组, 美国-01, check-interval=180, tolerance=100"
+        ));
+        assert!(
+            rendered
+                .content
+                .contains("server_check_url=http://www.gstatic.com/generate_204")
+        );
+    }
+
+    #[test]
+    fn test_filters() {
+        let rendered = render("quanx", &[]).unwrap();
+        let local = section(&rendered.content, "filter_local");
+
+        assert_eq!(local, vec!["geoip, cn, 直接连接", "final, 默认流量"]);
+        assert!(
+            rendered
+                .content
+                .contains("QuantumultX/China/China.list, tag=CN, force-policy=直接连接")
+        );
+    }
+}
+
+// ============================================================================
+// Tests for separators in names and fields
+// ============================================================================
+
+mod separator_tests {
+    use super::*;
+
+    const SEPARATORS: &str = r#"
+proxies:
+  - { name: "HK 01, IPLC", type: trojan, server: hk1.example.com, port: 443, password: p }
+  - { name: "HK=02", type: trojan, server: hk2.example.com, port: 443, password: p }
+  - { name: "HK 03", type: trojan, server: hk3.example.com, port: 443, password: "p,w" }
+  - { name: "HK 04", type: trojan, server: hk4.example.com, port: 443, password: p }
+"#;
+
+    #[test]
+    fn test_dropped_in_every_format() {
+        for target in ["surge", "loon", "quanx"] {
+            let rendered = render_input(SEPARATORS, target, &[]).unwrap();
+            let dropped: Vec<(&str, &str)> = rendered
+                .dropped
+                .iter()
+                .map(|d| (d.name.as_str(), d.reason.as_str()))
+                .collect();
+            let password_reason = match target {
+                "loon" => "a field contains ','",
+                _ => "'password' contains ','",
+            };
+
+            assert_eq!(
+                dropped,
+                vec![
+                    ("HK 01, IPLC", "name contains ',' or '='"),
+                    ("HK=02", "name contains ',' or '='"),
+                    ("HK 03", password_reason),
+                ],
+                "{}",
+                target
+            );
+            assert!(!rendered.content.contains("p,w"), "{}", target);
+            assert!(rendered.content.contains("HK 04"), "{}", target);
+        }
+    }
+
+    #[test]
+    fn test_group_members() {
+        let rendered = render_input(SEPARATORS, "surge", &[]).unwrap();
+        let groups = section(&rendered.content, "Proxy Group");
+
+        assert!(groups.contains(&"节点选择 = select, HK 04"));
+    }
+}
@@ -2,9 +2,94 @@
 //!
 //! Run with: cargo test
 
-use clash_sub::converter::{convert_subscription, get_proxy_name};
+use clash_sub::converter::{
+    ConvertOptions, GroupType, convert_subscription, convert_with_options, dedupe_proxies,
+    emit_proxy_list, get_proxy_name, is_info_node, merge_proxies, parse_subscription,
+    render_proxies, resolve_names, validate_proxy,
+};
+use clash_sub::proxy::Proxy;
 use serde_yaml::Value;
 
+/// Three info nodes, a Hong Kong, a US and a region-less proxy, converted by the option tests below
+const INPUT: &str = r#"
+proxies:
+  - name: "剩余流量: 100GB"
+    type: ss
+    server: info.example.com
+    port: 443
+    cipher: aes-256-gcm
+    password: password123
+  - name: "过期时间: 2024-12-31"
+    type: ss
+    server: info.example.com
+    port: 443
+    cipher: aes-256-gcm
+    password: password123
+  - name: "官网: example.de"
+    type: ss
+    server: info.example.com
+    port: 443
+    cipher: aes-256-gcm
+    password: password123
+  - name: "香港-01"
+    type: ss
+    server: hk1.example.com
+    port: 443
+    cipher: aes-256-gcm
+    password: password123
+  - name: "美国-01"
+    type: ss
+    server: us1.example.com
+    port: 443
+    cipher: aes-256-gcm
+    password: password123
+  - name: "Relay-01"
+    type: ss
+    server: relay1.example.com
+    port: 443
+    cipher: aes-256-gcm
+    password: password123
+"#;
+
+fn query(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+    pairs
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+/// Convert `INPUT` with the given query options, returning the YAML text
+fn convert_yaml(pairs: &[(&str, &str)]) -> Result<String, String> {
+    let options = ConvertOptions::from_query(&query(pairs)).map_err(|e| e.to_string())?;
+    Ok(convert_with_options(INPUT, &options).unwrap())
+}
+
+/// Convert `INPUT` with the given query options, returning the parsed config
+fn convert(pairs: &[(&str, &str)]) -> Result<Value, String> {
+    convert_yaml(pairs).map(|yaml| serde_yaml::from_str(&yaml).unwrap())
+}
+
+fn group<'a>(parsed: &'a Value, name: &str) -> Option<&'a Value> {
+    parsed["proxy-groups"]
+        .as_sequence()
+        .unwrap()
+        .iter()
+        .find(|g| g["name"].as_str() == Some(name))
+}
+
+fn rules(parsed: &Value) -> Vec<&str> {
+    parsed["rules"]
+        .as_sequence()
+        .unwrap()
+        .iter()
+        .filter_map(|r| r.as_str())
+        .collect()
+}
+
+fn names(proxies: &[Proxy]) -> Vec<String> {
+    proxies.iter().filter_map(Proxy::name).collect()
+}
+
 // ============================================================================
 // Tests for get_proxy_name
 // ============================================================================
@@ -266,6 +351,27 @@ proxies:
         assert!(result.unwrap_err().to_string().contains("Failed to parse"));
     }
 
+    #[test]
+    fn test_share_link_subscription() {
+        use base64::Engine;
+
+        let links = "trojan://secret@hk1.example.com:443#%E9%A6%99%E6%B8%AF-01\ntrojan://secret@jp1.example.com:443#%E6%97%A5%E6%9C%AC-01\n";
+        let input = base64::engine::general_purpose::STANDARD.encode(links);
+        let result = convert_subscription(&input);
+
+        assert!(result.is_ok());
+        let yaml = result.unwrap();
+
+        let parsed: Value = serde_yaml::from_str(&yaml).unwrap();
+        let proxies = parsed["proxies"].as_sequence().unwrap();
+        assert_eq!(proxies.len(), 2);
+        assert_eq!(proxies[0]["name"].as_str(), Some("香港-01"));
+        assert_eq!(proxies[0]["type"].as_str(), Some("trojan"));
+
+        assert!(yaml.contains("name: 香港负载组"));
+        assert!(yaml.contains("name: 日本负载组"));
+    }
+
     #[test]
     fn test_empty_proxies() {
         let input = r#"
@@ -289,10 +395,14 @@ proxies:
     type: ss
     server: hk1.example.com
     port: 443
+    cipher: aes-256-gcm
+    password: password123
   - name: "香港-02"
     type: ss
     server: hk2.example.com
     port: 443
+    cipher: aes-256-gcm
+    password: password123
 "#;
         let result = convert_subscription(input);
 
@@ -302,8 +412,8 @@ proxies:
         // Should have Hong Kong group
         assert!(yaml.contains("name: 香港负载组"));
 
-        // Should have "其他负载组" (always included)
-        assert!(yaml.contains("name: 其他负载组"));
+        // Every proxy matches a region, so there is no "其他负载组" to offer
+        assert!(!yaml.contains("其他负载组"));
 
         // Should NOT have Taiwan group (no matching proxies)
         let lines: Vec<&str> = yaml.lines().collect();
@@ -317,6 +427,45 @@ proxies:
         assert!(taiwan_count <= 1);
     }
 
+    #[test]
+    fn test_other_group_excludes_region_proxies() {
+        let input = r#"
+proxies:
+  - name: "香港-01"
+    type: ss
+    server: hk1.example.com
+    port: 443
+    cipher: aes-256-gcm
+    password: password123
+  - name: "UnknownNode"
+    type: ss
+    server: unknown.example.com
+    port: 443
+    cipher: aes-256-gcm
+    password: password123
+"#;
+        let yaml = convert_subscription(input).unwrap();
+        let parsed: Value = serde_yaml::from_str(&yaml).unwrap();
+        let groups = parsed["proxy-groups"].as_sequence().unwrap();
+
+        let other = groups
+            .iter()
+            .find(|g| g["name"].as_str() == Some("其他负载组"))
+            .unwrap();
+        assert_eq!(other["include-all"].as_bool(), Some(true));
+        assert!(other.get("filter").is_none());
+
+        let exclude = regex::Regex::new(other["exclude-filter"].as_str().unwrap()).unwrap();
+        // Proxies of any configured region are excluded, active or not
+        assert!(exclude.is_match("香港-01"));
+        assert!(exclude.is_match("🇯🇵 Tokyo"));
+        assert!(exclude.is_match("US-LA-01"));
+        assert!(exclude.is_match("剩余流量: 100GB"));
+        // Unmatched proxies remain
+        assert!(!exclude.is_match("UnknownNode"));
+        assert!(!exclude.is_match("Russia-01"));
+    }
+
     #[test]
     fn test_default_traffic_first_options() {
         let input = create_test_yaml();
@@ -347,123 +496,1231 @@ proxies:
 }
 
 // ============================================================================
-// Integration tests
+// Tests for info nodes
 // ============================================================================
 
-mod integration_tests {
+mod info_node_tests {
     use super::*;
 
     #[test]
-    fn test_full_workflow_with_mixed_regions() {
+    fn test_is_info_node() {
+        assert!(is_info_node("剩余流量: 100GB"));
+        assert!(is_info_node("过期时间: 2024-12-31"));
+        assert!(is_info_node("套餐到期: 2025-01-01"));
+        assert!(is_info_node("Traffic Reset: 5 days"));
+        assert!(is_info_node("Expire: 2025-01-01"));
+        assert!(!is_info_node("香港-01"));
+        assert!(!is_info_node("US-LA-01"));
+        assert!(is_info_node("剩余 100GB"));
+        assert!(is_info_node("2025-01-01 到期"));
+        assert!(is_info_node("官网：example.com"));
+
+        // Rate multipliers mention traffic without being info nodes
+        assert!(!is_info_node("香港 01 | 2倍流量"));
+        assert!(!is_info_node("流量倍率 0.5"));
+        assert!(!is_info_node("日本 IPLC 0.5x 流量"));
+        assert!(!is_info_node("Reset 01"));
+    }
+
+    #[test]
+    fn test_info_group_created() {
+        let parsed = convert(&[]).unwrap();
+
+        let info = group(&parsed, "信息").unwrap();
+        assert_eq!(info["type"].as_str(), Some("select"));
+        let proxies = info["proxies"].as_sequence().unwrap();
+        assert_eq!(proxies.len(), 3);
+        assert_eq!(proxies[0].as_str(), Some("剩余流量: 100GB"));
+
+        // Info nodes stay in the proxies list
+        assert_eq!(parsed["proxies"].as_sequence().unwrap().len(), 6);
+    }
+
+    #[test]
+    fn test_info_nodes_not_in_node_selector() {
+        let parsed = convert(&[]).unwrap();
+
+        let node_selector = group(&parsed, "节点选择").unwrap();
+        let proxies: Vec<&str> = node_selector["proxies"]
+            .as_sequence()
+            .unwrap()
+            .iter()
+            .filter_map(|p| p.as_str())
+            .collect();
+        assert_eq!(proxies, vec!["香港-01", "美国-01", "Relay-01"]);
+    }
+
+    #[test]
+    fn test_info_nodes_excluded_from_load_balance() {
+        let parsed = convert(&[]).unwrap();
+
+        for name in ["全部节点负载组", "香港负载组", "其他负载组"] {
+            let balanced = group(&parsed, name).unwrap();
+            let exclude = balanced["exclude-filter"].as_str().unwrap();
+            let re = regex::Regex::new(exclude).unwrap();
+            assert!(re.is_match("剩余流量: 100GB"), "{}", name);
+            assert!(!re.is_match("Unknown-01"), "{}", name);
+        }
+
+        // "官网: example.de" must not activate the Germany group
+        assert!(group(&parsed, "德国负载组").is_none());
+    }
+
+    #[test]
+    fn test_no_info_group_without_info_nodes() {
         let input = r#"
 proxies:
   - name: "香港-01"
     type: ss
     server: hk1.example.com
     port: 443
-  - name: "HK-02"
-    type: vmess
-    server: hk2.example.com
-    port: 443
-  - name: "台湾节点1"
-    type: trojan
-    server: tw1.example.com
-    port: 443
-  - name: "JP-Tokyo-01"
-    type: ss
-    server: jp1.example.com
-    port: 443
-  - name: "日本大阪"
-    type: ss
-    server: jp2.example.com
-    port: 443
-  - name: "Singapore-SG1"
-    type: ss
-    server: sg1.example.com
-    port: 443
-  - name: "US-LA-01"
-    type: ss
-    server: us1.example.com
-    port: 443
-  - name: "UnknownNode"
-    type: ss
-    server: unknown.example.com
-    port: 443
+    cipher: aes-256-gcm
+    password: password123
 "#;
-        let result = convert_subscription(input);
+        let yaml = convert_subscription(input).unwrap();
+        let parsed: Value = serde_yaml::from_str(&yaml).unwrap();
 
-        assert!(result.is_ok());
-        let yaml = result.unwrap();
+        assert!(group(&parsed, "信息").is_none());
+    }
+}
 
-        // Parse result
-        let parsed: Value = serde_yaml::from_str(&yaml).unwrap();
+// ============================================================================
+// Tests for group options
+// ============================================================================
 
-        // Check proxies are preserved
-        let proxies = parsed["proxies"].as_sequence().unwrap();
-        assert_eq!(proxies.len(), 8);
+mod group_options_tests {
+    use super::*;
 
-        // Check groups exist
+    #[test]
+    fn test_defaults() {
+        let options = ConvertOptions::from_query(&[]).unwrap();
+
+        assert_eq!(options.group_type, GroupType::LoadBalance);
+        assert_eq!(
+            options.balance.strategy.as_deref(),
+            Some("consistent-hashing")
+        );
+        assert_eq!(options.balance.tolerance, None);
+    }
+
+    #[test]
+    fn test_url_test_with_tolerance() {
+        let yaml = convert_yaml(&[
+            ("group_type", "url-test"),
+            ("tolerance", "50"),
+            ("lazy", "true"),
+        ])
+        .unwrap();
+
+        assert!(yaml.contains(".lb_common: &lb_common"));
+        assert!(yaml.contains("  tolerance: 50\n"));
+        assert!(yaml.contains("  lazy: true\n"));
+        assert!(!yaml.contains("strategy:"));
+        assert!(!yaml.contains("type: load-balance"));
+
+        let parsed: Value = serde_yaml::from_str(&yaml).unwrap();
         let groups = parsed["proxy-groups"].as_sequence().unwrap();
-        let group_names: Vec<String> = groups
-            .iter()
-            .map(|g| g["name"].as_str().unwrap().to_string())
-            .collect();
+        for name in ["全部节点负载组", "香港负载组", "美国负载组", "其他负载组"]
+        {
+            let group = groups
+                .iter()
+                .find(|g| g["name"].as_str() == Some(name))
+                .unwrap();
+            assert_eq!(group["type"].as_str(), Some("url-test"), "{}", name);
+            // Common settings come from the merge reference only
+            assert!(group.get("tolerance").is_none(), "{}", name);
+            assert!(group.get("url").is_none(), "{}", name);
+        }
 
-        assert!(group_names.contains(&"默认流量".to_string()));
-        assert!(group_names.contains(&"节点选择".to_string()));
-        assert!(group_names.contains(&"全部节点负载组".to_string()));
-        assert!(group_names.contains(&"香港负载组".to_string()));
-        assert!(group_names.contains(&"台湾负载组".to_string()));
-        assert!(group_names.contains(&"日本负载组".to_string()));
-        assert!(group_names.contains(&"新加坡负载组".to_string()));
-        assert!(group_names.contains(&"美国负载组".to_string()));
-        assert!(group_names.contains(&"其他负载组".to_string()));
-        assert!(group_names.contains(&"直接连接".to_string()));
+        let merge_count = yaml.matches("<<: *lb_common").count();
+        assert_eq!(merge_count, 4);
+    }
 
-        // Check rules
-        let rules = parsed["rules"].as_sequence().unwrap();
-        assert_eq!(rules.len(), 7);
+    #[test]
+    fn test_round_robin_with_timeout() {
+        let yaml = convert_yaml(&[("strategy", "round-robin"), ("timeout", "3000")]).unwrap();
+
+        assert!(yaml.contains("  strategy: round-robin\n"));
+        assert!(yaml.contains("  timeout: 3000\n"));
+        assert!(!yaml.contains("consistent-hashing"));
+        assert_eq!(yaml.matches("<<: *lb_common").count(), 4);
+        assert_eq!(yaml.matches("timeout:").count(), 1);
+    }
 
-        // Verify GEOSITE rules
-        assert_eq!(rules[0].as_str(), Some("GEOSITE,private,直接连接"));
-        assert_eq!(rules[1].as_str(), Some("GEOSITE,CN,直接连接"));
-        assert_eq!(rules[2].as_str(), Some("GEOSITE,apple-cn,直接连接"));
-        assert_eq!(rules[3].as_str(), Some("GEOSITE,steam@cn,直接连接"));
+    #[test]
+    fn test_fallback() {
+        let yaml = convert_yaml(&[("group_type", "fallback")]).unwrap();
+
+        assert!(yaml.contains("type: fallback"));
+        assert!(!yaml.contains("strategy:"));
+        assert_eq!(yaml.matches("<<: *lb_common").count(), 4);
+    }
+
+    #[test]
+    fn test_custom_health_check() {
+        let yaml = convert_yaml(&[
+            ("test_url", "https://cp.cloudflare.com/generate_204"),
+            ("interval", "300"),
+        ])
+        .unwrap();
+
+        assert!(yaml.contains("  url: https://cp.cloudflare.com/generate_204\n"));
+        assert!(yaml.contains("  interval: 300\n"));
+        assert!(!yaml.contains("gstatic"));
+        assert_eq!(yaml.matches("<<: *lb_common").count(), 4);
+
+        let mut parsed: Value = serde_yaml::from_str(&yaml).unwrap();
+        parsed.apply_merge().unwrap();
+        let all = group(&parsed, "全部节点负载组").unwrap();
         assert_eq!(
-            rules[4].as_str(),
-            Some("GEOSITE,category-games@cn,直接连接")
+            all["url"].as_str(),
+            Some("https://cp.cloudflare.com/generate_204")
         );
-        assert_eq!(rules[5].as_str(), Some("GEOIP,CN,直接连接"));
+        assert_eq!(all["interval"].as_u64(), Some(300));
     }
 
     #[test]
-    fn test_yaml_anchor_appears_once() {
-        let input = create_test_yaml();
-        let result = convert_subscription(&input);
+    fn test_invalid_options() {
+        let invalid: &[&[(&str, &str)]] = &[
+            &[("group_type", "random")],
+            &[("strategy", "fastest")],
+            &[("group_type", "url-test"), ("strategy", "round-robin")],
+            &[("tolerance", "50")],
+            &[("group_type", "url-test"), ("tolerance", "-1")],
+            &[("lazy", "maybe")],
+            &[("timeout", "soon")],
+            &[("test_url", "not a url")],
+            &[("test_url", "ftp://example.com/file")],
+            &[("test_url", "/generate_204")],
+            &[("interval", "0")],
+            &[("interval", "5")],
+            &[("interval", "100000")],
+        ];
+
+        for pairs in invalid {
+            assert!(
+                ConvertOptions::from_query(&query(pairs)).is_err(),
+                "{:?}",
+                pairs
+            );
+        }
+    }
+}
 
-        assert!(result.is_ok());
-        let yaml = result.unwrap();
+// ============================================================================
+// Tests for general settings
+// ============================================================================
 
-        // Anchor definition should appear exactly once
-        let anchor_count = yaml.matches(".lb_common: &lb_common").count();
-        assert_eq!(anchor_count, 1);
+mod general_settings_tests {
+    use super::*;
 
-        // Merge reference should appear multiple times
-        let merge_count = yaml.matches("<<: *lb_common").count();
-        assert!(merge_count > 0);
+    fn options(pairs: &[(&str, &str)]) -> Result<ConvertOptions, String> {
+        ConvertOptions::from_query(&query(pairs)).map_err(|e| e.to_string())
     }
 
-    fn create_test_yaml() -> String {
-        r#"
-proxies:
-  - name: "香港-01"
-    type: ss
-    server: hk1.example.com
-    port: 443
-  - name: "台湾-01"
-    type: ss
-    server: tw1.example.com
-    port: 443
+    #[test]
+    fn test_desktop_profile() {
+        let options = options(&[("profile", "desktop")]).unwrap();
+        let yaml = convert_with_options(INPUT, &options).unwrap();
+        let parsed: Value = serde_yaml::from_str(&yaml).unwrap();
+
+        assert_eq!(parsed["mixed-port"].as_u64(), Some(7890));
+        assert_eq!(parsed["allow-lan"].as_bool(), Some(false));
+        assert_eq!(parsed["mode"].as_str(), Some("rule"));
+        assert_eq!(parsed["log-level"].as_str(), Some("info"));
+        assert_eq!(parsed["ipv6"].as_bool(), Some(false));
+        assert_eq!(
+            parsed["external-controller"].as_str(),
+            Some("127.0.0.1:9090")
+        );
+        assert_eq!(parsed["unified-delay"].as_bool(), Some(true));
+        assert_eq!(parsed["tcp-concurrent"].as_bool(), Some(true));
+        assert!(parsed.get("secret").is_none());
+
+        // Settings come before the proxies
+        assert!(yaml.find("mixed-port:").unwrap() < yaml.find("proxies:").unwrap());
+    }
+
+    #[test]
+    fn test_router_profile_with_secret() {
+        let options = options(&[("profile", "router"), ("secret", "s3cret")]).unwrap();
+        let yaml = convert_with_options(INPUT, &options).unwrap();
+        let parsed: Value = serde_yaml::from_str(&yaml).unwrap();
+
+        assert_eq!(parsed["allow-lan"].as_bool(), Some(true));
+        assert_eq!(parsed["external-controller"].as_str(), Some("0.0.0.0:9090"));
+        assert_eq!(parsed["secret"].as_str(), Some("s3cret"));
+    }
+
+    #[test]
+    fn test_fake_ip_dns() {
+        let options = options(&[("dns", "fake-ip")]).unwrap();
+        let yaml = convert_with_options(INPUT, &options).unwrap();
+        let parsed: Value = serde_yaml::from_str(&yaml).unwrap();
+        let dns = &parsed["dns"];
+
+        assert_eq!(dns["enable"].as_bool(), Some(true));
+        assert_eq!(dns["enhanced-mode"].as_str(), Some("fake-ip"));
+        assert_eq!(dns["fake-ip-range"].as_str(), Some("198.18.0.1/16"));
+        let filter = dns["fake-ip-filter"].as_sequence().unwrap();
+        assert!(filter.iter().any(|d| d.as_str() == Some("*.lan")));
+        assert!(!dns["nameserver"].as_sequence().unwrap().is_empty());
+        assert!(dns.get("fallback").is_none());
+
+        let cn = dns["nameserver-policy"]["geosite:cn,private"]
+            .as_sequence()
+            .unwrap();
+        assert!(
+            cn.iter()
+                .any(|d| d.as_str() == Some("https://doh.pub/dns-query"))
+        );
+
+        // DNS alone does not add general settings
+        assert!(parsed.get("mixed-port").is_none());
+    }
+
+    #[test]
+    fn test_redir_host_dns_with_profile() {
+        let options = options(&[("dns", "redir-host"), ("profile", "desktop")]).unwrap();
+        let yaml = convert_with_options(INPUT, &options).unwrap();
+        let parsed: Value = serde_yaml::from_str(&yaml).unwrap();
+        let dns = &parsed["dns"];
+
+        assert_eq!(dns["enhanced-mode"].as_str(), Some("redir-host"));
+        assert!(dns.get("fake-ip-range").is_none());
+        assert!(!dns["fallback"].as_sequence().unwrap().is_empty());
+        assert_eq!(dns["fallback-filter"]["geoip-code"].as_str(), Some("CN"));
+
+        // General settings, then dns, then proxies
+        let general = yaml.find("mixed-port:").unwrap();
+        let dns = yaml.find("dns:").unwrap();
+        assert!(general < dns && dns < yaml.find("proxies:").unwrap());
+    }
+
+    #[test]
+    fn test_invalid_profiles() {
+        assert!(options(&[("profile", "server")]).is_err());
+        assert!(
+            options(&[("profile", "router")])
+                .unwrap_err()
+                .contains("secret")
+        );
+        assert!(options(&[("secret", "s3cret")]).is_err());
+        assert!(options(&[("dns", "doh")]).is_err());
+    }
+}
+
+// ============================================================================
+// Tests for rule providers
+// ============================================================================
+
+mod rule_provider_tests {
+    use super::*;
+
+    #[test]
+    fn test_loyalsoldier_template() {
+        let parsed = convert(&[("rules", "loyalsoldier")]).unwrap();
+        let reject = &parsed["rule-providers"]["reject"];
+
+        assert_eq!(reject["type"].as_str(), Some("http"));
+        assert_eq!(reject["behavior"].as_str(), Some("domain"));
+        assert!(reject["url"].as_str().unwrap().ends_with("/reject.txt"));
+        assert_eq!(reject["path"].as_str(), Some("./ruleset/reject.yaml"));
+        assert_eq!(
+            parsed["rule-providers"]["cncidr"]["behavior"].as_str(),
+            Some("ipcidr")
+        );
+
+        let rules = rules(&parsed);
+        assert!(rules.contains(&"RULE-SET,reject,REJECT"));
+        assert!(rules.contains(&"RULE-SET,google,默认流量"));
+        assert!(!rules.iter().any(|r| r.starts_with("GEOSITE")));
+        assert_eq!(
+            rules[rules.len() - 2..],
+            ["GEOIP,CN,直接连接", "MATCH,默认流量"]
+        );
+
+        // Every RULE-SET has a provider
+        let providers = parsed["rule-providers"].as_mapping().unwrap();
+        for rule in rules.iter().filter(|r| r.starts_with("RULE-SET,")) {
+            let name = rule.split(',').nth(1).unwrap();
+            assert!(providers.contains_key(name), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_acl4ssr_template() {
+        let parsed = convert(&[("rules", "acl4ssr")]).unwrap();
+
+        assert_eq!(
+            parsed["rule-providers"]["BanAD"]["behavior"].as_str(),
+            Some("classical")
+        );
+        assert!(rules(&parsed).contains(&"RULE-SET,BanAD,REJECT"));
+    }
+
+    #[test]
+    fn test_routes() {
+        let parsed = convert(&[
+            ("rules", "loyalsoldier"),
+            (
+                "routes",
+                "google:美国负载组, reject:DIRECT,proxy:日本负载组",
+            ),
+        ])
+        .unwrap();
+        let rules = rules(&parsed);
+
+        assert!(rules.contains(&"RULE-SET,google,美国负载组"));
+        assert!(rules.contains(&"RULE-SET,reject,DIRECT"));
+        // No Japanese proxies, so the group does not exist
+        assert!(rules.contains(&"RULE-SET,proxy,默认流量"));
+    }
+
+    #[test]
+    fn test_no_providers_by_default() {
+        let parsed = convert(&[]).unwrap();
+
+        assert!(parsed.get("rule-providers").is_none());
+        assert_eq!(rules(&parsed).len(), 7);
+    }
+
+    #[test]
+    fn test_invalid_rules() {
+        assert!(convert(&[("rules", "blackmatrix7")]).is_err());
+        assert!(convert(&[("routes", "google:美国负载组")]).is_err());
+        assert!(
+            convert(&[("rules", "loyalsoldier"), ("routes", "netflix:美国负载组")])
+                .unwrap_err()
+                .contains("netflix")
+        );
+        assert!(convert(&[("rules", "loyalsoldier"), ("routes", "google")]).is_err());
+        assert!(convert(&[("rules", "loyalsoldier"), ("routes", "google:")]).is_err());
+        // A typo in the group is not silently routed to 默认流量
+        assert_eq!(
+            convert(&[("rules", "loyalsoldier"), ("routes", "google:美国负载")]).unwrap_err(),
+            "Unknown group '美国负载' in route for 'google'"
+        );
+        assert!(
+            convert(&[("rules", "acl4ssr"), ("routes", "ProxyMedia:Netflix")])
+                .unwrap_err()
+                .contains("Netflix")
+        );
+    }
+}
+
+// ============================================================================
+// Tests for service groups
+// ============================================================================
+
+mod service_group_tests {
+    use super::*;
+
+    fn choices(group: &Value) -> Vec<&str> {
+        group["proxies"]
+            .as_sequence()
+            .unwrap()
+            .iter()
+            .filter_map(|p| p.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_service_groups() {
+        let parsed = convert(&[("services", "openai:美国负载组,netflix")]).unwrap();
+
+        let openai = group(&parsed, "OpenAI").unwrap();
+        assert_eq!(openai["type"].as_str(), Some("select"));
+        assert_eq!(
+            choices(openai),
+            vec![
+                "美国负载组",
+                "默认流量",
+                "全部节点负载组",
+                "香港负载组",
+                "其他负载组",
+                "直接连接"
+            ]
+        );
+        assert_eq!(choices(group(&parsed, "Netflix").unwrap())[0], "默认流量");
+        assert!(group(&parsed, "Telegram").is_none());
+
+        // Service groups follow 默认流量
+        let names: Vec<&str> = parsed["proxy-groups"]
+            .as_sequence()
+            .unwrap()
+            .iter()
+            .filter_map(|g| g["name"].as_str())
+            .collect();
+        assert_eq!(names[..4], ["默认流量", "OpenAI", "Netflix", "节点选择"]);
+    }
+
+    #[test]
+    fn test_service_rules_order() {
+        let parsed = convert(&[("services", "netflix,steam")]).unwrap();
+        let rules = rules(&parsed);
+        let position = |rule: &str| rules.iter().position(|r| *r == rule).unwrap();
+
+        assert!(position("GEOSITE,steam@cn,直接连接") < position("GEOSITE,steam,Steam"));
+        assert!(position("GEOSITE,netflix,Netflix") < position("GEOIP,CN,直接连接"));
+        assert!(rules.contains(&"GEOIP,netflix,Netflix,no-resolve"));
+        assert_eq!(rules.len(), 10);
+    }
+
+    #[test]
+    fn test_service_rules_with_rule_sets() {
+        let parsed = convert(&[("services", "youtube"), ("rules", "loyalsoldier")]).unwrap();
+        let rules = rules(&parsed);
+        let position = |rule: &str| rules.iter().position(|r| *r == rule).unwrap();
+
+        assert!(position("RULE-SET,reject,REJECT") < position("GEOSITE,youtube,YouTube"));
+        assert!(position("GEOSITE,youtube,YouTube") < position("RULE-SET,google,默认流量"));
+    }
+
+    #[test]
+    fn test_pinned_group_without_proxies() {
+        let parsed = convert(&[("services", "telegram:日本负载组")]).unwrap();
+
+        assert_eq!(choices(group(&parsed, "Telegram").unwrap())[0], "默认流量");
+    }
+
+    #[test]
+    fn test_rule_set_routed_to_service() {
+        let parsed = convert(&[
+            ("services", "netflix"),
+            ("rules", "acl4ssr"),
+            ("routes", "ProxyMedia:Netflix"),
+        ])
+        .unwrap();
+
+        assert!(rules(&parsed).contains(&"RULE-SET,ProxyMedia,Netflix"));
+    }
+
+    #[test]
+    fn test_invalid_services() {
+        assert!(convert(&[("services", "hbo")]).unwrap_err().contains("hbo"));
+        assert!(convert(&[("services", "netflix,netflix")]).is_err());
+        assert!(convert(&[("services", "netflix:")]).is_err());
+    }
+}
+
+// ============================================================================
+// Tests for proxy-provider output
+// ============================================================================
+
+mod proxy_provider_tests {
+    use super::*;
+
+    #[test]
+    fn test_providers_replace_proxies() {
+        let parsed = convert(&[
+            ("url", "https://a.example.com/sub|https://b.example.com/sub"),
+            ("providers", "1"),
+            ("lazy", "true"),
+        ])
+        .unwrap();
+
+        assert!(parsed.get("proxies").is_none());
+        let providers = parsed["proxy-providers"].as_mapping().unwrap();
+        assert_eq!(providers.len(), 2);
+
+        let first = &parsed["proxy-providers"]["provider-1"];
+        assert_eq!(first["type"].as_str(), Some("http"));
+        assert_eq!(first["url"].as_str(), Some("https://a.example.com/sub"));
+        assert_eq!(
+            first["path"].as_str(),
+            Some("./proxy_providers/provider-1.yaml")
+        );
+        assert_eq!(first["health-check"]["enable"].as_bool(), Some(true));
+        assert_eq!(first["health-check"]["interval"].as_u64(), Some(180));
+        assert_eq!(first["health-check"]["lazy"].as_bool(), Some(true));
+        assert_eq!(
+            parsed["proxy-providers"]["provider-2"]["url"].as_str(),
+            Some("https://b.example.com/sub")
+        );
+    }
+
+    #[test]
+    fn test_groups_use_providers() {
+        let parsed =
+            convert(&[("url", "https://a.example.com/sub"), ("providers", "true")]).unwrap();
+        let uses = |name: &str| {
+            group(&parsed, name).unwrap()["use"]
+                .as_sequence()
+                .unwrap()
+                .iter()
+                .filter_map(|p| p.as_str())
+                .collect::<Vec<_>>()
+        };
+
+        for name in [
+            "节点选择",
+            "信息",
+            "全部节点负载组",
+            "香港负载组",
+            "其他负载组",
+        ] {
+            assert_eq!(uses(name), vec!["provider-1"], "{}", name);
+            assert!(
+                group(&parsed, name).unwrap().get("include-all").is_none(),
+                "{}",
+                name
+            );
+        }
+
+        // Info nodes are split off by pattern instead of by name
+        let select = group(&parsed, "节点选择").unwrap();
+        assert!(select.get("proxies").is_none());
+        assert!(select["exclude-filter"].as_str().unwrap().contains("剩余"));
+        assert!(
+            group(&parsed, "信息").unwrap()["filter"]
+                .as_str()
+                .unwrap()
+                .contains("剩余")
+        );
+    }
+
+    #[test]
+    fn test_inline_by_default() {
+        let parsed = convert(&[("url", "https://a.example.com/sub"), ("providers", "0")]).unwrap();
+
+        assert!(parsed.get("proxy-providers").is_none());
+        assert_eq!(parsed["proxies"].as_sequence().unwrap().len(), 6);
+        assert_eq!(
+            group(&parsed, "香港负载组").unwrap()["include-all"].as_bool(),
+            Some(true)
+        );
+    }
+}
+
+// ============================================================================
+// Tests for emit_proxy_list
+// ============================================================================
+
+mod emit_proxy_list_tests {
+    use super::*;
+
+    #[test]
+    fn test_only_proxies() {
+        let input = r#"
+proxies:
+  - name: "香港-01"
+    type: ss
+    server: hk1.example.com
+    port: 443
+    cipher: aes-128-gcm
+    password: secret
+proxy-groups:
+  - name: Upstream
+    type: select
+    proxies: [香港-01]
+"#;
+        let yaml = emit_proxy_list(parse_subscription(input).unwrap()).unwrap();
+        let parsed: Value = serde_yaml::from_str(&yaml).unwrap();
+
+        let mapping = parsed.as_mapping().unwrap();
+        assert_eq!(mapping.len(), 1);
+        let proxies = parsed["proxies"].as_sequence().unwrap();
+        assert_eq!(proxies.len(), 1);
+        assert_eq!(proxies[0]["password"].as_str(), Some("secret"));
+    }
+
+    #[test]
+    fn test_share_links_are_normalized() {
+        let input =
+            "trojan://password@hk1.example.com:443?sni=hk1.example.com#%E9%A6%99%E6%B8%AF-01";
+        let yaml = emit_proxy_list(parse_subscription(input).unwrap()).unwrap();
+        let parsed: Value = serde_yaml::from_str(&yaml).unwrap();
+        let proxy = &parsed["proxies"][0];
+
+        assert_eq!(proxy["name"].as_str(), Some("香港-01"));
+        assert_eq!(proxy["type"].as_str(), Some("trojan"));
+        assert_eq!(proxy["port"].as_u64(), Some(443));
+    }
+}
+
+// ============================================================================
+// Tests for merge_proxies
+// ============================================================================
+
+mod merge_proxies_tests {
+    use super::*;
+
+    fn proxies(names: &[&str]) -> Vec<Proxy> {
+        names
+            .iter()
+            .map(|name| {
+                serde_yaml::from_str(&format!(
+                    "{{name: \"{}\", type: ss, server: example.com, port: 443}}",
+                    name
+                ))
+                .unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_concatenates_in_source_order() {
+        let merged = merge_proxies(vec![proxies(&["香港-01", "日本-01"]), proxies(&["US-01"])]);
+
+        assert_eq!(names(&merged), vec!["香港-01", "日本-01", "US-01"]);
+    }
+
+    #[test]
+    fn test_collisions_get_source_prefix() {
+        let merged = merge_proxies(vec![
+            proxies(&["香港-01", "日本-01"]),
+            proxies(&["香港-01", "US-01"]),
+            proxies(&["香港-01"]),
+        ]);
+
+        assert_eq!(
+            names(&merged),
+            vec![
+                "[1] 香港-01",
+                "日本-01",
+                "[2] 香港-01",
+                "US-01",
+                "[3] 香港-01"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_single_source_unchanged() {
+        let merged = merge_proxies(vec![proxies(&["香港-01", "香港-01"])]);
+
+        assert_eq!(names(&merged), vec!["香港-01", "香港-01"]);
+    }
+}
+
+// ============================================================================
+// Tests for proxy validation
+// ============================================================================
+
+mod validation_tests {
+    use super::*;
+
+    fn validate(yaml: &str) -> Result<(), String> {
+        let value: Value = serde_yaml::from_str(yaml).unwrap();
+        validate_proxy(&Proxy::from(value))
+    }
+
+    #[test]
+    fn test_valid_proxies() {
+        let input = r#"
+proxies:
+  - { name: a, type: ss, server: a.com, port: 443, cipher: 2022-blake3-aes-128-gcm, password: p }
+  - { name: b, type: vmess, server: b.com, port: 443, uuid: 11111111-1111-1111-1111-111111111111, alterId: 0, cipher: auto, network: ws }
+  - { name: c, type: vless, server: c.com, port: 443, uuid: custom-id }
+  - { name: d, type: tuic, server: d.com, port: 443, token: t }
+  - { name: e, type: hysteria2, server: e.com, port: 443, password: p, obfs: salamander }
+"#;
+        for proxy in parse_subscription(input).unwrap() {
+            assert_eq!(validate_proxy(&proxy), Ok(()), "{:?}", proxy.name());
+        }
+    }
+
+    #[test]
+    fn test_missing_fields() {
+        assert_eq!(
+            validate("{ name: a, type: ss, server: a.com, port: 443, password: p }"),
+            Err("missing field `cipher`".to_string())
+        );
+        assert_eq!(
+            validate("{ name: a, type: trojan, server: '', port: 443, password: p }"),
+            Err("missing server".to_string())
+        );
+        assert_eq!(
+            validate("{ name: a, type: trojan, server: a.com, port: 0, password: p }"),
+            Err("invalid port 0".to_string())
+        );
+        assert_eq!(
+            validate(
+                "{ name: a, type: wireguard, server: a.com, port: 51820, private-key: k, public-key: k }"
+            ),
+            Err("missing ip".to_string())
+        );
+    }
+
+    #[test]
+    fn test_bad_values() {
+        assert_eq!(
+            validate(
+                "{ name: a, type: ss, server: a.com, port: 443, cipher: aes-512-gcm, password: p }"
+            ),
+            Err("unsupported cipher 'aes-512-gcm'".to_string())
+        );
+        assert_eq!(
+            validate(
+                "{ name: a, type: vless, server: a.com, port: 443, uuid: not-a-uuid-but-far-too-long-to-be-an-id }"
+            ),
+            Err("invalid uuid 'not-a-uuid-but-far-too-long-to-be-an-id'".to_string())
+        );
+        assert_eq!(
+            validate(
+                "{ name: a, type: hysteria2, server: a.com, port: 443, password: p, obfs: xor }"
+            ),
+            Err("unsupported obfs 'xor'".to_string())
+        );
+    }
+
+    #[test]
+    fn test_loose_fields() {
+        // Clash reads quoted booleans and numbers, and leaves unknown transports to the client
+        let proxy = Proxy::from(
+            serde_yaml::from_str::<Value>(
+                "{ name: a, type: vmess, server: a.com, port: '443', uuid: id, udp: 'true', tls: \"false\" }",
+            )
+            .unwrap(),
+        );
+        assert_eq!(validate_proxy(&proxy), Ok(()));
+        let value = Value::from(proxy);
+        assert_eq!(value["udp"].as_bool(), Some(true));
+        assert_eq!(value["tls"].as_bool(), Some(false));
+
+        assert_eq!(
+            validate(
+                "{ name: a, type: trojan, server: a.com, port: 443, password: p, network: kcp }"
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            validate(
+                "{ name: a, type: trojan, server: a.com, port: 443, password: p, udp: maybe }"
+            ),
+            Err("invalid boolean 'maybe'".to_string())
+        );
+    }
+
+    #[test]
+    fn test_unknown_types_pass_through() {
+        assert_eq!(validate("{ name: a, type: ssr, server: a.com }"), Ok(()));
+        // Names are resolved after validation
+        assert_eq!(validate("{ type: ssr, server: a.com }"), Ok(()));
+        assert_eq!(
+            validate("{ name: a, server: a.com }"),
+            Err("missing type".to_string())
+        );
+    }
+
+    #[test]
+    fn test_invalid_proxies_removed_and_reported() {
+        let input = r#"
+proxies:
+  - { name: 香港-01, type: ss, server: hk.com, port: 443, cipher: aes-256-gcm, password: p }
+  - { name: 香港-02, type: ss, server: hk.com, port: 443, cipher: rot13, password: p }
+  - { name: 日本-01, type: vmess, server: jp.com, port: 443, alterId: 0 }
+"#;
+        let proxies = parse_subscription(input).unwrap();
+        let conversion = render_proxies(proxies, &ConvertOptions::default()).unwrap();
+
+        let parsed: Value = serde_yaml::from_str(&conversion.content).unwrap();
+        assert_eq!(parsed["proxies"].as_sequence().unwrap().len(), 1);
+        assert!(!conversion.content.contains("香港-02"));
+
+        let invalid: Vec<(&str, &str)> = conversion
+            .report
+            .invalid
+            .iter()
+            .map(|p| (p.name.as_str(), p.reason.as_str()))
+            .collect();
+        assert_eq!(
+            invalid,
+            vec![
+                ("香港-02", "unsupported cipher 'rot13'"),
+                ("日本-01", "missing field `uuid`"),
+            ]
+        );
+        assert!(conversion.report.dropped.is_empty());
+    }
+
+    #[test]
+    fn test_debug_option() {
+        let query = |pairs: &[(&str, &str)]| -> Vec<(String, String)> {
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect()
+        };
+        assert!(!ConvertOptions::from_query(&[]).unwrap().debug);
+        assert!(
+            ConvertOptions::from_query(&query(&[("debug", "1")]))
+                .unwrap()
+                .debug
+        );
+        assert!(ConvertOptions::from_query(&query(&[("debug", "maybe")])).is_err());
+    }
+}
+
+// ============================================================================
+// Tests for resolve_names
+// ============================================================================
+
+mod resolve_names_tests {
+    use super::*;
+
+    #[test]
+    fn test_duplicates_get_suffixes() {
+        let input = r#"
+proxies:
+  - { name: 香港-01, type: ss, server: a.com, port: 443, cipher: aes-256-gcm, password: p }
+  - { name: 香港-01, type: ss, server: b.com, port: 443, cipher: aes-256-gcm, password: p }
+  - { name: 香港-01, type: ssr, server: c.com, port: 443 }
+"#;
+        let mut proxies = parse_subscription(input).unwrap();
+        let renamed = resolve_names(&mut proxies);
+
+        assert_eq!(
+            names(&proxies),
+            vec!["香港-01", "香港-01 (2)", "香港-01 (3)"]
+        );
+        assert_eq!(renamed.len(), 2);
+        assert_eq!(renamed[0].from, "香港-01");
+        assert_eq!(renamed[0].to, "香港-01 (2)");
+    }
+
+    #[test]
+    fn test_suffix_skips_upstream_names() {
+        let input = r#"
+proxies:
+  - { name: A, type: trojan, server: a.com, port: 443, password: p }
+  - { name: A, type: trojan, server: b.com, port: 443, password: p }
+  - { name: A (2), type: trojan, server: c.com, port: 443, password: p }
+"#;
+        let mut proxies = parse_subscription(input).unwrap();
+        resolve_names(&mut proxies);
+
+        assert_eq!(names(&proxies), vec!["A", "A (3)", "A (2)"]);
+    }
+
+    #[test]
+    fn test_missing_names() {
+        let input = r#"
+proxies:
+  - { type: trojan, server: a.com, port: 443, password: p }
+  - { name: null, type: trojan, server: a.com, port: 443, password: p }
+  - { name: "", type: ssr, server: c.com }
+"#;
+        let mut proxies = parse_subscription(input).unwrap();
+        assert!(!matches!(proxies[0], Proxy::Unknown(_)));
+        let renamed = resolve_names(&mut proxies);
+
+        assert_eq!(
+            names(&proxies),
+            vec!["trojan a.com:443", "trojan a.com:443 (2)", "ssr"]
+        );
+        assert_eq!(renamed.len(), 3);
+        assert_eq!(renamed[0].from, "");
+    }
+
+    #[test]
+    fn test_selector_lists_unique_names() {
+        let input = r#"
+proxies:
+  - { name: 香港-01, type: ss, server: a.com, port: 443, cipher: aes-256-gcm, password: p }
+  - { name: 香港-01, type: ss, server: b.com, port: 443, cipher: aes-256-gcm, password: p }
+  - { type: ss, server: c.com, port: 443, cipher: aes-256-gcm, password: p }
+"#;
+        let conversion = render_proxies(
+            parse_subscription(input).unwrap(),
+            &ConvertOptions::default(),
+        )
+        .unwrap();
+        let parsed: Value = serde_yaml::from_str(&conversion.content).unwrap();
+
+        let proxy_names: Vec<&str> = parsed["proxies"]
+            .as_sequence()
+            .unwrap()
+            .iter()
+            .filter_map(|p| p["name"].as_str())
+            .collect();
+        assert_eq!(proxy_names, vec!["香港-01", "香港-01 (2)", "ss c.com:443"]);
+
+        let selector = parsed["proxy-groups"]
+            .as_sequence()
+            .unwrap()
+            .iter()
+            .find(|g| g["name"].as_str() == Some("节点选择"))
+            .unwrap();
+        let members: Vec<&str> = selector["proxies"]
+            .as_sequence()
+            .unwrap()
+            .iter()
+            .filter_map(|p| p.as_str())
+            .collect();
+        for name in &proxy_names {
+            assert_eq!(members.iter().filter(|m| *m == name).count(), 1);
+        }
+        assert_eq!(conversion.report.renamed.len(), 2);
+    }
+}
+
+// ============================================================================
+// Tests for dedupe
+// ============================================================================
+
+mod dedupe_tests {
+    use super::*;
+
+    const INPUT: &str = r#"
+proxies:
+  - { name: 剩余流量：100GB, type: ss, server: 127.0.0.1, port: 1, cipher: aes-256-gcm, password: p }
+  - { name: 过期时间：2025-01-01, type: ss, server: 127.0.0.1, port: 1, cipher: aes-256-gcm, password: p }
+  - { name: 香港-01, type: ss, server: hk.example.com, port: 443, cipher: aes-256-gcm, password: p }
+  - { name: 香港-01 IPLC, type: ss, server: HK.example.com, port: "443", password: p, cipher: aes-256-gcm }
+  - { name: 香港-02, type: ss, server: hk.example.com, port: 443, cipher: aes-256-gcm, password: other }
+"#;
+
+    #[test]
+    fn test_same_connection_kept_once() {
+        let (proxies, duplicates) = dedupe_proxies(parse_subscription(INPUT).unwrap());
+
+        let names: Vec<String> = proxies.iter().filter_map(Proxy::name).collect();
+        assert_eq!(
+            names,
+            vec![
+                "剩余流量：100GB",
+                "过期时间：2025-01-01",
+                "香港-01",
+                "香港-02"
+            ]
+        );
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!(duplicates[0].name, "香港-01 IPLC");
+        assert_eq!(duplicates[0].kept, "香港-01");
+    }
+
+    #[test]
+    fn test_off_by_default() {
+        let proxies = parse_subscription(INPUT).unwrap();
+        let conversion = render_proxies(proxies, &ConvertOptions::default()).unwrap();
+
+        assert!(conversion.content.contains("香港-01 IPLC"));
+        assert!(conversion.report.duplicates.is_empty());
+    }
+
+    #[test]
+    fn test_dedupe_option() {
+        let query = vec![("dedupe".to_string(), "1".to_string())];
+        let options = ConvertOptions::from_query(&query).unwrap();
+        assert!(options.dedupe);
+
+        let proxies = parse_subscription(INPUT).unwrap();
+        let conversion = render_proxies(proxies, &options).unwrap();
+
+        assert!(!conversion.content.contains("香港-01 IPLC"));
+        assert_eq!(conversion.report.duplicates.len(), 1);
+    }
+}
+
+// ============================================================================
+// Integration tests
+// ============================================================================
+
+mod integration_tests {
+    use super::*;
+
+    #[test]
+    fn test_full_workflow_with_mixed_regions() {
+        let input = r#"
+proxies:
+  - name: "香港-01"
+    type: ss
+    server: hk1.example.com
+    port: 443
+    cipher: aes-256-gcm
+    password: password123
+  - name: "HK-02"
+    type: vmess
+    server: hk2.example.com
+    port: 443
+    uuid: 11111111-1111-1111-1111-111111111111
+    alterId: 0
+    cipher: auto
+  - name: "台湾节点1"
+    type: trojan
+    server: tw1.example.com
+    port: 443
+    password: password123
+  - name: "JP-Tokyo-01"
+    type: ss
+    server: jp1.example.com
+    port: 443
+    cipher: aes-256-gcm
+    password: password123
+  - name: "日本大阪"
+    type: ss
+    server: jp2.example.com
+    port: 443
+    cipher: aes-256-gcm
+    password: password123
+  - name: "Singapore-SG1"
+    type: ss
+    server: sg1.example.com
+    port: 443
+    cipher: aes-256-gcm
+    password: password123
+  - name: "US-LA-01"
+    type: ss
+    server: us1.example.com
+    port: 443
+    cipher: aes-256-gcm
+    password: password123
+  - name: "UnknownNode"
+    type: ss
+    server: unknown.example.com
+    port: 443
+    cipher: aes-256-gcm
+    password: password123
+"#;
+        let result = convert_subscription(input);
+
+        assert!(result.is_ok());
+        let yaml = result.unwrap();
+
+        // Parse result
+        let parsed: Value = serde_yaml::from_str(&yaml).unwrap();
+
+        // Check proxies are preserved
+        let proxies = parsed["proxies"].as_sequence().unwrap();
+        assert_eq!(proxies.len(), 8);
+
+        // Check groups exist
+        let groups = parsed["proxy-groups"].as_sequence().unwrap();
+        let group_names: Vec<String> = groups
+            .iter()
+            .map(|g| g["name"].as_str().unwrap().to_string())
+            .collect();
+
+        assert!(group_names.contains(&"默认流量".to_string()));
+        assert!(group_names.contains(&"节点选择".to_string()));
+        assert!(group_names.contains(&"全部节点负载组".to_string()));
+        assert!(group_names.contains(&"香港负载组".to_string()));
+        assert!(group_names.contains(&"台湾负载组".to_string()));
+        assert!(group_names.contains(&"日本负载组".to_string()));
+        assert!(group_names.contains(&"新加坡负载组".to_string()));
+        assert!(group_names.contains(&"美国负载组".to_string()));
+        assert!(group_names.contains(&"其他负载组".to_string()));
+        assert!(group_names.contains(&"直接连接".to_string()));
+
+        // Check rules
+        let rules = parsed["rules"].as_sequence().unwrap();
+        assert_eq!(rules.len(), 7);
+
+        // Verify GEOSITE rules
+        assert_eq!(rules[0].as_str(), Some("GEOSITE,private,直接连接"));
+        assert_eq!(rules[1].as_str(), Some("GEOSITE,CN,直接连接"));
+        assert_eq!(rules[2].as_str(), Some("GEOSITE,apple-cn,直接连接"));
+        assert_eq!(rules[3].as_str(), Some("GEOSITE,steam@cn,直接连接"));
+        assert_eq!(
+            rules[4].as_str(),
+            Some("GEOSITE,category-games@cn,直接连接")
+        );
+        assert_eq!(rules[5].as_str(), Some("GEOIP,CN,直接连接"));
+    }
+
+    #[test]
+    fn test_yaml_anchor_appears_once() {
+        let input = create_test_yaml();
+        let result = convert_subscription(&input);
+
+        assert!(result.is_ok());
+        let yaml = result.unwrap();
+
+        // Anchor definition should appear exactly once
+        let anchor_count = yaml.matches(".lb_common: &lb_common").count();
+        assert_eq!(anchor_count, 1);
+
+        // Merge reference should appear multiple times
+        let merge_count = yaml.matches("<<: *lb_common").count();
+        assert!(merge_count > 0);
+    }
+
+    #[test]
+    fn test_merge_keys_resolve_to_group_settings() {
+        let input = create_test_yaml();
+        let yaml = convert_subscription(&input).unwrap();
+
+        let mut parsed: Value = serde_yaml::from_str(&yaml).unwrap();
+        parsed.apply_merge().unwrap();
+        let groups = parsed["proxy-groups"].as_sequence().unwrap();
+
+        let hong_kong = groups
+            .iter()
+            .find(|g| g["name"].as_str() == Some("香港负载组"))
+            .unwrap();
+        assert_eq!(
+            hong_kong["url"].as_str(),
+            Some("http://www.gstatic.com/generate_204")
+        );
+        assert_eq!(hong_kong["interval"].as_u64(), Some(180));
+        assert_eq!(hong_kong["strategy"].as_str(), Some("consistent-hashing"));
+
+        let selector = groups
+            .iter()
+            .find(|g| g["name"].as_str() == Some("节点选择"))
+            .unwrap();
+        assert!(selector.get("url").is_none());
+    }
+
+    #[test]
+    fn test_proxy_names_do_not_confuse_emitter() {
+        let input = r#"
+proxies:
+  - name: "type: load-balance"
+    type: ss
+    server: a.example.com
+    port: 443
+    cipher: aes-256-gcm
+    password: password123
+  - name: "url: http://www.gstatic.com/generate_204"
+    type: ss
+    server: b.example.com
+    port: 443
+    cipher: aes-256-gcm
+    password: password123
+  - name: "香港-01"
+    type: ss
+    server: hk1.example.com
+    port: 443
+    cipher: aes-256-gcm
+    password: password123
+"#;
+        let yaml = convert_subscription(input).unwrap();
+        let parsed: Value = serde_yaml::from_str(&yaml).unwrap();
+
+        let proxies = parsed["proxies"].as_sequence().unwrap();
+        assert_eq!(proxies.len(), 3);
+        assert_eq!(proxies[0]["name"].as_str(), Some("type: load-balance"));
+        assert_eq!(proxies[0]["port"].as_u64(), Some(443));
+
+        let groups = parsed["proxy-groups"].as_sequence().unwrap();
+        let selector = groups
+            .iter()
+            .find(|g| g["name"].as_str() == Some("节点选择"))
+            .unwrap();
+        assert_eq!(selector["proxies"].as_sequence().unwrap().len(), 3);
+
+        // One merge per balanced group: 全部节点负载组, 香港负载组, 其他负载组
+        assert_eq!(yaml.matches("<<: *lb_common").count(), 3);
+    }
+
+    fn create_test_yaml() -> String {
+        r#"
+proxies:
+  - name: "香港-01"
+    type: ss
+    server: hk1.example.com
+    port: 443
+    cipher: aes-256-gcm
+    password: password123
+  - name: "台湾-01"
+    type: ss
+    server: tw1.example.com
+    port: 443
+    cipher: aes-256-gcm
+    password: password123
 "#
         .to_string()
     }
@@ -0,0 +1,142 @@
+//! Tests for the filter module
+//!
+//! Run with: cargo test
+
+use clash_sub::converter::{ConvertOptions, parse_subscription, render_proxies};
+use clash_sub::filter::{FilterField, ProxyFilter, filter_proxies};
+use clash_sub::proxy::Proxy;
+
+const INPUT: &str = r#"
+proxies:
+  - { name: "剩余流量：100GB", type: ss, server: 127.0.0.1, port: 1, cipher: aes-256-gcm, password: p }
+  - { name: 香港-01, type: ss, server: hk1.example.com, port: 443, cipher: aes-256-gcm, password: p }
+  - { name: 香港-02 倍率 3x, type: trojan, server: hk2.example.com, port: 443, password: p }
+  - { name: 日本-01 IPv6, type: trojan, server: jp1.example.cn, port: 443, password: p }
+  - { name: 美国-01, type: ssr, server: us1.example.com, port: 443 }
+"#;
+
+fn filters(patterns: &[&str]) -> Vec<ProxyFilter> {
+    patterns.iter().map(|p| p.parse().unwrap()).collect()
+}
+
+fn filtered_names(include: &[&str], exclude: &[&str]) -> Vec<String> {
+    let proxies = parse_subscription(INPUT).unwrap();
+    let (kept, _) = filter_proxies(proxies, &filters(include), &filters(exclude));
+    kept.iter().filter_map(Proxy::name).collect()
+}
+
+// ============================================================================
+// Tests for ProxyFilter parsing
+// ============================================================================
+
+mod parse_tests {
+    use super::*;
+
+    #[test]
+    fn test_fields() {
+        let field = |text: &str| text.parse::<ProxyFilter>().unwrap().field;
+        assert_eq!(field("香港|日本"), FilterField::Name);
+        assert_eq!(field("type:ssr"), FilterField::Type);
+        assert_eq!(field("server:\\.cn$"), FilterField::Server);
+        // Other prefixes are part of the name pattern
+        assert_eq!(field("IPLC:1x"), FilterField::Name);
+    }
+
+    #[test]
+    fn test_invalid_pattern() {
+        let err = "倍率(".parse::<ProxyFilter>().unwrap_err();
+        assert!(err.to_string().starts_with("Invalid filter '倍率('"));
+    }
+
+    #[test]
+    fn test_from_query() {
+        let query: Vec<(String, String)> = [
+            ("include", "香港"),
+            ("include", "日本"),
+            ("exclude", "IPv6"),
+        ]
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        let options = ConvertOptions::from_query(&query).unwrap();
+        assert_eq!(options.include.len(), 2);
+        assert_eq!(options.exclude.len(), 1);
+
+        let query = vec![("exclude".to_string(), "[".to_string())];
+        assert!(ConvertOptions::from_query(&query).is_err());
+    }
+
+    #[test]
+    fn test_not_with_providers() {
+        for key in ["include", "exclude", "dedupe", "rename", "emoji", "number"] {
+            let query: Vec<(String, String)> = [
+                ("url", "https://example.com/sub"),
+                ("providers", "true"),
+                (key, "1"),
+            ]
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+            let err = ConvertOptions::from_query(&query).unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                format!("'{}' is not supported with providers", key)
+            );
+        }
+    }
+}
+
+// ============================================================================
+// Tests for filter_proxies
+// ============================================================================
+
+mod filter_proxies_tests {
+    use super::*;
+
+    #[test]
+    fn test_exclude_by_name() {
+        assert_eq!(
+            filtered_names(&[], &["倍率\\s*[2-9]", "(?i)ipv6"]),
+            vec!["剩余流量：100GB", "香港-01", "美国-01"]
+        );
+    }
+
+    #[test]
+    fn test_include_keeps_info_nodes() {
+        assert_eq!(
+            filtered_names(&["香港"], &[]),
+            vec!["剩余流量：100GB", "香港-01", "香港-02 倍率 3x"]
+        );
+        assert_eq!(
+            filtered_names(&["香港"], &["剩余"]),
+            vec!["香港-01", "香港-02 倍率 3x"]
+        );
+    }
+
+    #[test]
+    fn test_type_and_server() {
+        assert_eq!(
+            filtered_names(&["type:^trojan$"], &["server:\\.cn$"]),
+            vec!["剩余流量：100GB", "香港-02 倍率 3x"]
+        );
+        assert_eq!(
+            filtered_names(&[], &["type:ssr"]),
+            vec![
+                "剩余流量：100GB",
+                "香港-01",
+                "香港-02 倍率 3x",
+                "日本-01 IPv6"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reported() {
+        let query = vec![("exclude".to_string(), "倍率".to_string())];
+        let options = ConvertOptions::from_query(&query).unwrap();
+        let conversion = render_proxies(parse_subscription(INPUT).unwrap(), &options).unwrap();
+
+        assert!(!conversion.content.contains("倍率"));
+        assert_eq!(conversion.report.filtered, vec!["香港-02 倍率 3x"]);
+    }
+}
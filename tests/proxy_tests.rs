@@ -0,0 +1,158 @@
+//! Tests for the typed proxy model
+//!
+//! Run with: cargo test
+
+use clash_sub::proxy::{OneOrMany, Proxy};
+use clash_sub::share_link::to_link;
+use serde_yaml::Value;
+
+fn proxy(yaml: &str) -> Proxy {
+    serde_yaml::from_str(yaml).unwrap()
+}
+
+// ============================================================================
+// Tests for reading proxies
+// ============================================================================
+
+mod typed_tests {
+    use super::*;
+
+    #[test]
+    fn test_shadowsocks() {
+        let Proxy::Shadowsocks(ss) = proxy(
+            "{name: HK, type: ss, server: hk.example.com, port: 8388, cipher: aes-128-gcm, password: secret, udp: true, plugin: obfs, plugin-opts: {mode: tls, host: bing.com}}",
+        ) else {
+            panic!("not shadowsocks");
+        };
+
+        assert_eq!(ss.endpoint.name, "HK");
+        assert_eq!(ss.endpoint.port, 8388);
+        assert_eq!(ss.cipher, "aes-128-gcm");
+        assert_eq!(ss.udp, Some(true));
+        assert_eq!(ss.plugin.as_deref(), Some("obfs"));
+        assert_eq!(ss.plugin_opts.unwrap().host.as_deref(), Some("bing.com"));
+    }
+
+    #[test]
+    fn test_vless_with_tls_and_transport() {
+        let Proxy::Vless(vless) = proxy(
+            "{name: JP, type: vless, server: jp.example.com, port: 443, uuid: abc, tls: true, servername: www.apple.com, alpn: h2, reality-opts: {public-key: key, short-id: 1234}, network: ws, ws-opts: {path: /ws, headers: {Host: cdn.example.com}}}",
+        ) else {
+            panic!("not vless");
+        };
+
+        assert!(vless.tls_enabled());
+        assert_eq!(vless.tls.server_name(), Some("www.apple.com"));
+        assert_eq!(vless.tls.alpn, Some(OneOrMany::One("h2".to_string())));
+        assert_eq!(
+            vless.reality_opts.unwrap().short_id.as_deref(),
+            Some("1234")
+        );
+        assert_eq!(vless.transport.network(), "ws");
+        assert_eq!(
+            vless.transport.ws_opts.unwrap().host(),
+            Some("cdn.example.com")
+        );
+    }
+
+    #[test]
+    fn test_numbers_and_strings_are_lenient() {
+        let Proxy::Trojan(trojan) =
+            proxy("{name: 1, type: trojan, server: tr.example.com, port: '443', password: 123456}")
+        else {
+            panic!("not trojan");
+        };
+
+        assert_eq!(trojan.endpoint.name, "1");
+        assert_eq!(trojan.endpoint.port, 443);
+        assert_eq!(trojan.password, "123456");
+    }
+
+    #[test]
+    fn test_hysteria2_bandwidth() {
+        let Proxy::Hysteria2(hy2) = proxy(
+            "{name: HY, type: hysteria2, server: hy.example.com, port: 443, password: p, up: 50 Mbps, down: 200}",
+        ) else {
+            panic!("not hysteria2");
+        };
+
+        assert_eq!(hy2.up_mbps(), Some(50));
+        assert_eq!(hy2.down_mbps(), Some(200));
+    }
+
+    #[test]
+    fn test_unknown_type() {
+        let proxy = proxy("{name: SSR, type: ssr, server: a.com, port: 443, obfs: plain}");
+
+        assert!(matches!(proxy, Proxy::Unknown(_)));
+        assert_eq!(proxy.type_name(), "ssr");
+        assert_eq!(proxy.name().as_deref(), Some("SSR"));
+        assert_eq!(to_link(&proxy), Err("unsupported type 'ssr'".to_string()));
+    }
+
+    #[test]
+    fn test_invalid_fields_fall_back_to_unknown() {
+        let proxy = proxy("{name: Broken, type: ss, server: a.com, port: 443, password: x}");
+
+        assert!(matches!(proxy, Proxy::Unknown(_)));
+        assert_eq!(proxy.type_name(), "ss");
+        assert_eq!(proxy.name().as_deref(), Some("Broken"));
+        assert_eq!(to_link(&proxy), Err("invalid ss proxy".to_string()));
+    }
+}
+
+// ============================================================================
+// Tests for writing proxies back
+// ============================================================================
+
+mod round_trip_tests {
+    use super::*;
+
+    fn round_trip(yaml: &str) {
+        let original: Value = serde_yaml::from_str(yaml).unwrap();
+        let written = Value::from(Proxy::from(original.clone()));
+        assert_eq!(written, original);
+    }
+
+    #[test]
+    fn test_unknown_keys_are_kept() {
+        round_trip(
+            "{name: US, type: vmess, server: us.example.com, port: 443, uuid: abc, alterId: 0, cipher: auto, ip-version: ipv4, smux: {enabled: true}, network: ws, ws-opts: {path: /ws, max-early-data: 2048, headers: {Host: cdn.example.com}}}",
+        );
+        round_trip(
+            "{name: WG, type: wireguard, server: 1.2.3.4, port: 51820, private-key: key, public-key: peer, ip: 10.0.0.2, mtu: 1420, reserved: [1, 2, 3]}",
+        );
+        round_trip(
+            "{name: H, type: vmess, server: h.com, port: 80, uuid: abc, network: http, http-opts: {method: GET, path: [/a, /b], headers: {Host: [a.com]}}}",
+        );
+    }
+
+    #[test]
+    fn test_unknown_proxies_are_kept() {
+        round_trip("{name: SSR, type: ssr, server: a.com, port: 443, obfs: plain}");
+        round_trip("{name: Broken, type: ss, server: a.com, port: 443}");
+    }
+
+    #[test]
+    fn test_name_and_type_first() {
+        let proxy = proxy("{server: a.com, port: 443, type: trojan, password: x, name: TR}");
+        let yaml = serde_yaml::to_string(&proxy).unwrap();
+        let keys: Vec<&str> = yaml
+            .lines()
+            .filter_map(|line| line.split(':').next())
+            .collect();
+
+        assert_eq!(keys, vec!["name", "type", "server", "port", "password"]);
+    }
+
+    #[test]
+    fn test_set_name() {
+        let mut typed = proxy("{name: A, type: trojan, server: a.com, port: 443, password: x}");
+        let mut unknown = proxy("{name: B, type: ssr, server: b.com, port: 443}");
+        typed.set_name("[1] A".to_string());
+        unknown.set_name("[2] B".to_string());
+
+        assert_eq!(typed.name().as_deref(), Some("[1] A"));
+        assert_eq!(Value::from(unknown)["name"].as_str(), Some("[2] B"));
+    }
+}
@@ -0,0 +1,238 @@
+//! Tests for the region module
+//!
+//! Run with: cargo test
+
+use clash_sub::converter::{ConvertOptions, convert_with_options};
+use clash_sub::region::{default_regions, parse_region_rules, region_matchers};
+use serde_yaml::Value;
+
+// ============================================================================
+// Tests for parse_region_rules
+// ============================================================================
+
+mod parse_region_rules_tests {
+    use super::*;
+
+    fn names(text: &str) -> Vec<String> {
+        parse_region_rules(text)
+            .unwrap()
+            .into_iter()
+            .map(|rule| rule.name)
+            .collect()
+    }
+
+    #[test]
+    fn test_default_table() {
+        let regions = default_regions();
+
+        assert_eq!(regions.len(), 14);
+        assert_eq!(regions[0].name, "香港负载组");
+        assert_eq!(regions[0].emoji.as_deref(), Some("🇭🇰"));
+        assert!(regions.iter().all(|rule| rule.order.is_some()));
+    }
+
+    #[test]
+    fn test_bare_list_is_merged_into_defaults() {
+        let names = names(
+            r#"[{"name": "俄罗斯负载组", "pattern": "(?i)俄|russia", "emoji": "🇷🇺", "order": 55}]"#,
+        );
+
+        assert_eq!(names.len(), 15);
+        assert_eq!(names[4], "美国负载组");
+        assert_eq!(names[5], "俄罗斯负载组");
+        assert_eq!(names[6], "韩国负载组");
+    }
+
+    #[test]
+    fn test_rules_without_order_come_last() {
+        let names = names(
+            r#"
+regions:
+  - name: 印度负载组
+    pattern: "(?i)印度|india"
+"#,
+        );
+
+        assert_eq!(names.last().map(String::as_str), Some("印度负载组"));
+    }
+
+    #[test]
+    fn test_same_name_overrides_default() {
+        let regions = parse_region_rules(
+            r#"[{"name": "日本负载组", "keywords": ["日本", "japan"], "order": 30}]"#,
+        )
+        .unwrap();
+
+        assert_eq!(regions.len(), 14);
+        let japan = regions.iter().find(|r| r.name == "日本负载组").unwrap();
+        assert_eq!(japan.keywords, vec!["日本", "japan"]);
+        assert_eq!(japan.pattern, None);
+        assert_eq!(japan.emoji, None);
+    }
+
+    #[test]
+    fn test_replace_table() {
+        let names = names(
+            r#"
+replace: true
+regions:
+  - name: 荷兰负载组
+    pattern: "(?i)荷兰|netherlands"
+  - name: 香港负载组
+    pattern: "(?i)港|hong kong"
+"#,
+        );
+
+        assert_eq!(names, vec!["荷兰负载组", "香港负载组"]);
+    }
+
+    #[test]
+    fn test_invalid_pattern() {
+        let result = parse_region_rules(r#"[{"name": "坏", "pattern": "(unclosed"}]"#);
+
+        assert!(result.is_err());
+        assert!(result.unwrap_err().to_string().contains("坏"));
+    }
+
+    #[test]
+    fn test_invalid_document() {
+        assert!(parse_region_rules("not a table").is_err());
+        assert!(parse_region_rules(r#"[{"name": "", "pattern": "x"}]"#).is_err());
+    }
+}
+
+// ============================================================================
+// Tests for region matching
+// ============================================================================
+
+mod region_matching_tests {
+    use super::*;
+
+    /// Names of the default regions a proxy name falls into
+    fn regions_of(name: &str) -> Vec<String> {
+        region_matchers(&default_regions())
+            .unwrap()
+            .into_iter()
+            .filter(|matcher| matcher.is_match(name))
+            .map(|matcher| matcher.name)
+            .collect()
+    }
+
+    #[test]
+    fn test_collision_matrix() {
+        let cases: &[(&str, &[&str])] = &[
+            // ASCII codes inside words no longer match
+            ("Vocal-01", &[]),
+            ("Cascade", &[]),
+            ("Russia-01", &[]),
+            ("Trojan-DE-01", &["德国负载组"]),
+            ("Auto Select", &[]),
+            ("Premium-US-Plus", &["美国负载组"]),
+            // 新加坡 is not Canada
+            ("新加坡-01", &["新加坡负载组"]),
+            ("加拿大-01", &["加拿大负载组"]),
+            // Codes next to digits, separators and CJK
+            ("HK01", &["香港负载组"]),
+            ("香港HK-02", &["香港负载组"]),
+            ("Hong-Kong 03", &["香港负载组"]),
+            ("HongKong 04", &["香港负载组"]),
+            ("USA 01", &["美国负载组"]),
+            ("Singapore-SG1", &["新加坡负载组"]),
+            ("sg_02", &["新加坡负载组"]),
+            // Flag emojis
+            ("🇭🇰 Premium 01", &["香港负载组"]),
+            ("🇯🇵 01", &["日本负载组"]),
+            // Multiple regions stay possible
+            ("香港-日本 IPLC", &["香港负载组", "日本负载组"]),
+            ("UnknownNode", &[]),
+        ];
+
+        for (name, expected) in cases {
+            assert_eq!(regions_of(name), *expected, "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_longer_names_of_other_regions_are_excluded() {
+        let regions = default_regions();
+        let canada = regions.iter().find(|r| r.name == "加拿大负载组").unwrap();
+        let hong_kong = regions.iter().find(|r| r.name == "香港负载组").unwrap();
+
+        assert_eq!(canada.exclude_filter(&regions), Some("新加坡".to_string()));
+        assert_eq!(hong_kong.exclude_filter(&regions), None);
+    }
+
+    #[test]
+    fn test_filter_is_ordered_longest_first() {
+        let filter = default_regions()[3].filter();
+
+        assert!(filter.starts_with("(?i)新加坡|新|"));
+        assert!(filter.contains("🇸🇬"));
+    }
+
+    #[test]
+    fn test_raw_pattern_is_kept() {
+        let regions = parse_region_rules(
+            r#"[{"name": "俄罗斯负载组", "pattern": "(?i)^RU\\d+", "keywords": ["俄罗斯"]}]"#,
+        )
+        .unwrap();
+        let matchers = region_matchers(&regions).unwrap();
+        let russia = matchers.iter().find(|m| m.name == "俄罗斯负载组").unwrap();
+
+        assert!(russia.is_match("RU01"));
+        assert!(russia.is_match("俄罗斯 莫斯科"));
+        assert!(!russia.is_match("Russia"));
+    }
+}
+
+// ============================================================================
+// Tests for custom regions in conversion
+// ============================================================================
+
+mod custom_region_conversion_tests {
+    use super::*;
+
+    #[test]
+    fn test_custom_region_group_created() {
+        let input = r#"
+proxies:
+  - name: "Russia-01"
+    type: ss
+    server: ru1.example.com
+    port: 443
+    cipher: aes-256-gcm
+    password: password123
+  - name: "香港-01"
+    type: ss
+    server: hk1.example.com
+    port: 443
+    cipher: aes-256-gcm
+    password: password123
+"#;
+        let options = ConvertOptions {
+            regions: parse_region_rules(
+                r#"[{"name": "俄罗斯负载组", "keywords": ["俄", "russia"], "order": 15}]"#,
+            )
+            .unwrap(),
+            ..Default::default()
+        };
+        let yaml = convert_with_options(input, &options).unwrap();
+        let parsed: Value = serde_yaml::from_str(&yaml).unwrap();
+
+        let group_names: Vec<&str> = parsed["proxy-groups"]
+            .as_sequence()
+            .unwrap()
+            .iter()
+            .filter_map(|g| g["name"].as_str())
+            .collect();
+
+        let hk = group_names.iter().position(|n| *n == "香港负载组").unwrap();
+        let ru = group_names
+            .iter()
+            .position(|n| *n == "俄罗斯负载组")
+            .unwrap();
+        assert_eq!(ru, hk + 1);
+        // Both proxies match a region
+        assert!(!group_names.contains(&"其他负载组"));
+    }
+}
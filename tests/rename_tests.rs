@@ -0,0 +1,241 @@
+//! Tests for the rename module
+//!
+//! Run with: cargo test
+
+use clash_sub::converter::{ConvertOptions, parse_subscription, render_proxies};
+use clash_sub::proxy::Proxy;
+use clash_sub::rename::{RenameRule, rename_proxies};
+use serde_yaml::Value;
+
+fn options(pairs: &[(&str, &str)]) -> ConvertOptions {
+    let query: Vec<(String, String)> = pairs
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    ConvertOptions::from_query(&query).unwrap()
+}
+
+/// Proxy names of a subscription after renaming
+fn renamed(names: &[&str], pairs: &[(&str, &str)]) -> Vec<String> {
+    let mut proxies: Vec<Proxy> = names
+        .iter()
+        .map(|name| {
+            let yaml = format!(
+                "{{ name: '{}', type: ssr, server: a.com, port: 443 }}",
+                name
+            );
+            Proxy::from(serde_yaml::from_str::<Value>(&yaml).unwrap())
+        })
+        .collect();
+    rename_proxies(&mut proxies, &options(pairs)).unwrap();
+    proxies.iter().filter_map(Proxy::name).collect()
+}
+
+// ============================================================================
+// Tests for RenameRule
+// ============================================================================
+
+mod rename_rule_tests {
+    use super::*;
+
+    fn apply(rule: &str, name: &str) -> String {
+        rule.parse::<RenameRule>().unwrap().apply(name)
+    }
+
+    #[test]
+    fn test_remove() {
+        assert_eq!(
+            apply(r"\s*\|.*$", "🇭🇰 Hong Kong 01 | 1x | IPLC"),
+            "🇭🇰 Hong Kong 01"
+        );
+        assert_eq!(apply("(?i)iplc", "HK IPLC 01"), "HK  01");
+    }
+
+    #[test]
+    fn test_replace() {
+        assert_eq!(apply("Hong Kong@香港", "Hong Kong 01"), "香港 01");
+        assert_eq!(apply(r"^(\w+)-(\d+)$@$2 $1", "HK-01"), "01 HK");
+        // The pattern may contain `@`
+        assert_eq!(apply("a@b@c", "xa@by"), "xcy");
+    }
+
+    #[test]
+    fn test_invalid_pattern() {
+        let err = "(@x".parse::<RenameRule>().unwrap_err();
+        assert!(err.to_string().starts_with("Invalid rename '(@x'"));
+
+        let query = vec![("rename".to_string(), "[".to_string())];
+        assert!(ConvertOptions::from_query(&query).is_err());
+    }
+
+    #[test]
+    fn test_empty_pattern() {
+        let err = "@.*$".parse::<RenameRule>().unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("Invalid rename '@.*$': empty pattern")
+        );
+        assert!("".parse::<RenameRule>().is_err());
+
+        // A trailing `@` removes a pattern that contains `@`
+        assert_eq!(apply("@.*$@", "加拿大 01@IPLC"), "加拿大 01");
+    }
+}
+
+// ============================================================================
+// Tests for rename_proxies
+// ============================================================================
+
+mod rename_proxies_tests {
+    use super::*;
+
+    #[test]
+    fn test_rules_in_order() {
+        assert_eq!(
+            renamed(
+                &["Hong Kong 01 | 1x", "Japan 02 | 2x"],
+                &[("rename", r"\s*\|.*$"), ("rename", "Hong Kong@香港")]
+            ),
+            vec!["香港 01", "Japan 02"]
+        );
+    }
+
+    #[test]
+    fn test_emoji() {
+        assert_eq!(
+            renamed(
+                &["香港-01", "🇯🇵 Japan 01", "Russia 01", "剩余流量：100GB"],
+                &[("emoji", "1")]
+            ),
+            vec!["🇭🇰 香港-01", "🇯🇵 Japan 01", "Russia 01", "剩余流量：100GB"]
+        );
+    }
+
+    #[test]
+    fn test_emoji_replaces_other_flags() {
+        assert_eq!(
+            renamed(
+                &["🇯🇵 香港-01", "Hong Kong 🇭🇰 02", "🇺🇸🇭🇰 HK 03"],
+                &[("emoji", "1")]
+            ),
+            vec!["🇭🇰 香港-01", "🇭🇰 Hong Kong 02", "🇭🇰 HK 03"]
+        );
+    }
+
+    #[test]
+    fn test_emoji_group_references() {
+        let input = r#"
+proxies:
+  - { name: 香港-01, type: trojan, server: a.com, port: 443, password: p }
+  - { name: 美国-01, type: trojan, server: b.com, port: 443, password: p }
+"#;
+        let options = options(&[
+            ("emoji", "1"),
+            ("services", "openai:美国负载组"),
+            ("rules", "loyalsoldier"),
+            ("routes", "google:香港负载组"),
+        ]);
+        let conversion = render_proxies(parse_subscription(input).unwrap(), &options).unwrap();
+        let parsed: Value = serde_yaml::from_str(&conversion.content).unwrap();
+        let groups = parsed["proxy-groups"].as_sequence().unwrap();
+        let members = |name: &str| -> Vec<String> {
+            let group = groups
+                .iter()
+                .find(|g| g["name"].as_str() == Some(name))
+                .unwrap();
+            serde_yaml::from_value(group["proxies"].clone()).unwrap()
+        };
+
+        assert!(members("默认流量").contains(&"🇭🇰 香港负载组".to_string()));
+        assert_eq!(members("OpenAI")[0], "🇺🇸 美国负载组");
+        assert!(conversion.content.contains("RULE-SET,google,🇭🇰 香港负载组"));
+        assert!(!conversion.content.contains(",香港负载组"));
+    }
+
+    #[test]
+    fn test_number() {
+        assert_eq!(
+            renamed(
+                &[
+                    "香港 1",
+                    "香港 BGP",
+                    "香港-3",
+                    "日本 7",
+                    "Vocal Cascade",
+                    "剩余流量：100GB"
+                ],
+                &[("rename", r"\s*BGP"), ("number", "1")]
+            ),
+            vec![
+                "香港 01",
+                "香港 02",
+                "香港 03",
+                "日本 7",
+                "Vocal Cascade",
+                "剩余流量：100GB"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_number_needs_separator() {
+        assert_eq!(
+            renamed(
+                &["香港 IPv4", "香港 IPv6", "x2", "x3", "5", "6"],
+                &[("number", "1")]
+            ),
+            vec!["香港 IPv4", "香港 IPv6", "x2", "x3", "01", "02"]
+        );
+    }
+
+    #[test]
+    fn test_groups_use_new_names() {
+        let input = r#"
+proxies:
+  - { name: "Hong Kong 01 | IPLC", type: ss, server: a.com, port: 443, cipher: aes-256-gcm, password: p }
+  - { name: "Hong Kong 02 | IPLC", type: ss, server: b.com, port: 443, cipher: aes-256-gcm, password: p }
+"#;
+        let options = options(&[("rename", r"\s*\|.*$"), ("emoji", "1")]);
+        let conversion = render_proxies(parse_subscription(input).unwrap(), &options).unwrap();
+        let parsed: Value = serde_yaml::from_str(&conversion.content).unwrap();
+
+        let names: Vec<&str> = parsed["proxies"]
+            .as_sequence()
+            .unwrap()
+            .iter()
+            .filter_map(|p| p["name"].as_str())
+            .collect();
+        assert_eq!(names, vec!["🇭🇰 Hong Kong 01", "🇭🇰 Hong Kong 02"]);
+
+        let groups = parsed["proxy-groups"].as_sequence().unwrap();
+        let selector = groups
+            .iter()
+            .find(|g| g["name"].as_str() == Some("节点选择"))
+            .unwrap();
+        assert!(
+            selector["proxies"]
+                .as_sequence()
+                .unwrap()
+                .contains(&Value::from("🇭🇰 Hong Kong 01"))
+        );
+        assert!(
+            groups
+                .iter()
+                .any(|g| g["name"].as_str() == Some("🇭🇰 香港负载组"))
+        );
+    }
+
+    #[test]
+    fn test_collisions_resolved() {
+        let input = r#"
+proxies:
+  - { name: "HK 01 | 1x", type: trojan, server: a.com, port: 443, password: p }
+  - { name: "HK 01 | 2x", type: trojan, server: b.com, port: 443, password: p }
+"#;
+        let options = options(&[("rename", r"\s*\|.*$")]);
+        let conversion = render_proxies(parse_subscription(input).unwrap(), &options).unwrap();
+
+        assert_eq!(conversion.report.renamed.len(), 1);
+        assert_eq!(conversion.report.renamed[0].to, "HK 01 (2)");
+    }
+}
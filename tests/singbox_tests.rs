@@ -0,0 +1,277 @@
+//! Tests for the sing-box output
+//!
+//! Run with: cargo test
+
+use clash_sub::converter::{
+    ConvertOptions, convert_with_options, parse_subscription, render_proxies,
+};
+use serde_json::Value;
+
+const INPUT: &str = r#"
+proxies:
+  - name: "剩余流量: 100GB"
+    type: ss
+    server: info.example.com
+    port: 443
+    cipher: aes-128-gcm
+    password: info
+  - name: "香港-01"
+    type: ss
+    server: hk1.example.com
+    port: 8388
+    cipher: aes-128-gcm
+    password: secret
+    plugin: obfs
+    plugin-opts:
+      mode: tls
+      host: bing.com
+  - name: "美国-01"
+    type: vmess
+    server: us1.example.com
+    port: 443
+    uuid: 11111111-1111-1111-1111-111111111111
+    alterId: 0
+    cipher: auto
+    tls: true
+    servername: us1.example.com
+    network: ws
+    ws-opts:
+      path: /ws
+      headers:
+        Host: us1.example.com
+  - name: "日本-01"
+    type: vless
+    server: jp1.example.com
+    port: 443
+    uuid: 22222222-2222-2222-2222-222222222222
+    flow: xtls-rprx-vision
+    tls: true
+    servername: www.microsoft.com
+    client-fingerprint: chrome
+    reality-opts:
+      public-key: pubkey
+      short-id: abcd
+  - name: "新加坡-01"
+    type: hysteria2
+    server: sg1.example.com
+    port: 443
+    password: hy2
+    up: "50 Mbps"
+    obfs: salamander
+    obfs-password: obfs
+    sni: sg1.example.com
+  - name: "Snell-01"
+    type: snell
+    server: snell.example.com
+    port: 443
+    psk: psk
+"#;
+
+fn convert(pairs: &[(&str, &str)]) -> Result<Value, String> {
+    let query: Vec<(String, String)> = std::iter::once(("target", "singbox"))
+        .chain(pairs.iter().copied())
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    let options = ConvertOptions::from_query(&query).map_err(|e| e.to_string())?;
+    let json = convert_with_options(INPUT, &options).map_err(|e| e.to_string())?;
+    Ok(serde_json::from_str(&json).unwrap())
+}
+
+fn outbound<'a>(config: &'a Value, tag: &str) -> Option<&'a Value> {
+    config["outbounds"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|o| o["tag"].as_str() == Some(tag))
+}
+
+fn members(outbound: &Value) -> Vec<&str> {
+    outbound["outbounds"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|m| m.as_str())
+        .collect()
+}
+
+// ============================================================================
+// Tests for proxy outbounds
+// ============================================================================
+
+mod outbound_tests {
+    use super::*;
+
+    #[test]
+    fn test_shadowsocks_with_plugin() {
+        let config = convert(&[]).unwrap();
+        let ss = outbound(&config, "香港-01").unwrap();
+
+        assert_eq!(ss["type"], "shadowsocks");
+        assert_eq!(ss["server"], "hk1.example.com");
+        assert_eq!(ss["server_port"], 8388);
+        assert_eq!(ss["method"], "aes-128-gcm");
+        assert_eq!(ss["password"], "secret");
+        assert_eq!(ss["plugin"], "obfs-local");
+        assert_eq!(ss["plugin_opts"], "obfs=tls;obfs-host=bing.com");
+    }
+
+    #[test]
+    fn test_vmess_with_ws_transport() {
+        let config = convert(&[]).unwrap();
+        let vmess = outbound(&config, "美国-01").unwrap();
+
+        assert_eq!(vmess["type"], "vmess");
+        assert_eq!(vmess["security"], "auto");
+        assert_eq!(vmess["alter_id"], 0);
+        assert_eq!(vmess["tls"]["server_name"], "us1.example.com");
+        assert_eq!(vmess["transport"]["type"], "ws");
+        assert_eq!(vmess["transport"]["path"], "/ws");
+        assert_eq!(vmess["transport"]["headers"]["Host"], "us1.example.com");
+    }
+
+    #[test]
+    fn test_vless_reality() {
+        let config = convert(&[]).unwrap();
+        let vless = outbound(&config, "日本-01").unwrap();
+
+        assert_eq!(vless["flow"], "xtls-rprx-vision");
+        assert_eq!(vless["tls"]["utls"]["fingerprint"], "chrome");
+        assert_eq!(vless["tls"]["reality"]["public_key"], "pubkey");
+        assert_eq!(vless["tls"]["reality"]["short_id"], "abcd");
+    }
+
+    #[test]
+    fn test_hysteria2() {
+        let config = convert(&[]).unwrap();
+        let hy2 = outbound(&config, "新加坡-01").unwrap();
+
+        assert_eq!(hy2["type"], "hysteria2");
+        assert_eq!(hy2["up_mbps"], 50);
+        assert_eq!(hy2["obfs"]["type"], "salamander");
+        assert_eq!(hy2["obfs"]["password"], "obfs");
+        assert_eq!(hy2["tls"]["enabled"], true);
+    }
+
+    #[test]
+    fn test_unsupported_types_are_dropped() {
+        let config = convert(&[]).unwrap();
+
+        assert!(outbound(&config, "Snell-01").is_none());
+        // Its only group is empty and left out, as are references to it
+        assert!(outbound(&config, "其他负载组").is_none());
+        assert!(!members(outbound(&config, "默认流量").unwrap()).contains(&"其他负载组"));
+        assert!(!members(outbound(&config, "节点选择").unwrap()).contains(&"Snell-01"));
+    }
+
+    #[test]
+    fn test_tuic_v4_is_dropped() {
+        let input = r#"
+proxies:
+  - { name: V5, type: tuic, server: a.com, port: 443, uuid: 11111111-1111-1111-1111-111111111111, password: p }
+  - { name: V4, type: tuic, server: b.com, port: 443, token: t }
+"#;
+        let query = vec![("target".to_string(), "singbox".to_string())];
+        let options = ConvertOptions::from_query(&query).unwrap();
+        let conversion = render_proxies(parse_subscription(input).unwrap(), &options).unwrap();
+        let config: Value = serde_json::from_str(&conversion.content).unwrap();
+
+        let v5 = outbound(&config, "V5").unwrap();
+        assert_eq!(v5["type"], "tuic");
+        assert_eq!(v5["password"], "p");
+        assert!(outbound(&config, "V4").is_none());
+        assert_eq!(conversion.report.dropped.len(), 1);
+        assert_eq!(conversion.report.dropped[0].name, "V4");
+        assert_eq!(
+            conversion.report.dropped[0].reason,
+            "only TUIC v5 is supported"
+        );
+    }
+}
+
+// ============================================================================
+// Tests for groups and route
+// ============================================================================
+
+mod group_route_tests {
+    use super::*;
+
+    #[test]
+    fn test_groups() {
+        let config = convert(&[]).unwrap();
+
+        let select = outbound(&config, "默认流量").unwrap();
+        assert_eq!(select["type"], "selector");
+        assert_eq!(
+            members(select)[..3],
+            ["节点选择", "直接连接", "全部节点负载组"]
+        );
+
+        let hk = outbound(&config, "香港负载组").unwrap();
+        assert_eq!(hk["type"], "urltest");
+        assert_eq!(members(hk), vec!["香港-01"]);
+        assert_eq!(hk["interval"], "180s");
+        assert_eq!(hk["url"], "http://www.gstatic.com/generate_204");
+
+        // Info nodes stay out of the balanced groups
+        let all = outbound(&config, "全部节点负载组").unwrap();
+        assert!(!members(all).contains(&"剩余流量: 100GB"));
+        assert_eq!(
+            members(outbound(&config, "信息").unwrap()),
+            vec!["剩余流量: 100GB"]
+        );
+
+        assert_eq!(
+            members(outbound(&config, "直接连接").unwrap()),
+            vec!["DIRECT"]
+        );
+        assert_eq!(outbound(&config, "DIRECT").unwrap()["type"], "direct");
+    }
+
+    #[test]
+    fn test_url_test_tolerance() {
+        let config = convert(&[("group_type", "url-test"), ("tolerance", "50")]).unwrap();
+
+        assert_eq!(
+            outbound(&config, "全部节点负载组").unwrap()["tolerance"],
+            50
+        );
+    }
+
+    #[test]
+    fn test_route_rules() {
+        let config = convert(&[("services", "netflix")]).unwrap();
+        let route = &config["route"];
+
+        assert_eq!(route["final"], "默认流量");
+        assert_eq!(route["rules"][0]["action"], "sniff");
+        assert_eq!(route["rules"][1]["rule_set"][0], "geosite-private");
+        assert_eq!(route["rules"][1]["outbound"], "直接连接");
+
+        let rules = route["rules"].as_array().unwrap();
+        assert!(
+            rules
+                .iter()
+                .any(|r| r["rule_set"][0] == "geoip-netflix" && r["outbound"] == "Netflix")
+        );
+
+        // Every referenced rule set is declared once
+        let rule_sets = route["rule_set"].as_array().unwrap();
+        for rule in rules.iter().skip(1) {
+            let tag = &rule["rule_set"][0];
+            assert_eq!(rule_sets.iter().filter(|s| s["tag"] == *tag).count(), 1);
+        }
+        let cn = rule_sets.iter().find(|s| s["tag"] == "geosite-cn").unwrap();
+        assert_eq!(cn["type"], "remote");
+        assert!(cn["url"].as_str().unwrap().ends_with("/geosite/cn.srs"));
+    }
+
+    #[test]
+    fn test_clash_only_options() {
+        for key in ["profile", "dns", "rules", "providers"] {
+            let error = convert(&[(key, "x")]).unwrap_err();
+            assert!(error.contains("target=clash"), "{}", key);
+        }
+        let query = vec![("target".to_string(), "quantumult".to_string())];
+        assert!(ConvertOptions::from_query(&query).is_err());
+    }
+}